@@ -0,0 +1,445 @@
+//! The calculation core: everything the UI, the CLI and the comparison show is computed here.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{Library, Project},
+    panel_orientation::efficiency,
+    quotes::Date,
+    simulation::{monthly, simulate_year, typical_days, Day, Month, SimulationInput},
+    tax::CostCategory,
+    units::Energy,
+};
+
+/// All results for a project
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Analysis {
+    pub panel_count: usize,
+    pub area_m2: f32,
+    pub kwp: f32,
+    /// Hardware plus installation as the operator pays it: gross, or net if the VAT is
+    /// deducted. The economics are based on this.
+    pub price_sum: f32,
+    pub price_net: f32,
+    pub price_gross: f32,
+    /// 0-1, from orientation and inclination
+    pub efficiency: f32,
+    pub yield_year_kwh: f32,
+    /// What the consumption would cost per year using only the grid
+    pub regular_energy_cost: f32,
+    pub consumption_covered_kwh: f32,
+    pub amount_to_sell_kwh: f32,
+    /// PV energy lost to the feed-in limit
+    pub curtailed_kwh: f32,
+    pub grid_import_kwh: f32,
+    /// 0-1. Share of the consumption covered by PV
+    pub autarky: f32,
+    /// 0-1. Share of the yield that is consumed on site
+    pub self_consumption: f32,
+    /// Income tax on the feed-in revenue per year
+    pub income_tax: f32,
+    /// Savings plus feed-in revenue per year, after income tax
+    pub combined_benefit: f32,
+    pub payback_years: f32,
+    /// The investment put on a deposit for the payback time instead
+    pub alternative_investment: f32,
+    /// Net present value over the lifetime, discounted with the deposit interest rate.
+    /// Includes battery replacements.
+    pub npv: f32,
+    /// Equivalent full cycles of all batteries per year
+    pub battery_cycles_per_year: f32,
+    /// When the first battery bank wears out, if that is within the lifetime
+    pub battery_replacement_year: Option<f32>,
+    /// Cost of all battery replacements within the lifetime, not discounted
+    pub battery_replacement_cost: f32,
+    /// Production, consumption and energy flows per month
+    pub monthly: Vec<Month>,
+    /// Clear sky solstices and the best and worst day of the year
+    pub days: Vec<Day>,
+}
+
+/// What the system costs, known without simulating it
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Investment {
+    pub net: f32,
+    pub gross: f32,
+    /// Gross, or net if the VAT is deducted
+    pub paid: f32,
+}
+
+impl Investment {
+    pub fn new(project: &Project, library: &Library) -> Self {
+        let net = project.sum(library).price_sum.amount();
+        let gross = net
+            + project
+                .tax
+                .vat_groups(&project.bill_of_materials(library))
+                .iter()
+                .map(|g| g.vat.amount())
+                .sum::<f32>();
+        let paid = if project.tax.vat_deductible {
+            net
+        } else {
+            gross
+        };
+        Self { net, gross, paid }
+    }
+}
+
+pub fn analyze(project: &Project, library: &Library) -> Analysis {
+    let res = project.sum(library);
+    let efficiency = efficiency(
+        project.azimuth_from_equator().abs(),
+        project.panel_angle_deg,
+    );
+    let kwp = res.peak_power.kw();
+
+    // how much of the power we generate can be self-used, hour by hour
+    let input = simulation_input(project, library);
+    let yield_year_kwh = input.production_year_kwh;
+    let hours = simulate_year(&input);
+    let monthly = monthly(&hours);
+    let consumption_covered = monthly.iter().map(|m| m.self_consumption_kwh).sum::<f32>();
+    let amount_to_sell = monthly.iter().map(|m| m.feed_in_kwh).sum::<f32>();
+    let grid_import_kwh = monthly.iter().map(|m| m.grid_import_kwh).sum::<f32>();
+    let revenue = Energy::from_kwh(amount_to_sell) * project.price_sell;
+    let income_tax = project.tax.income_tax(revenue);
+    let combined_benefit =
+        (Energy::from_kwh(consumption_covered) * project.price_buy + revenue - income_tax).amount();
+    let Investment {
+        net: price_net,
+        gross: price_gross,
+        paid: price_sum,
+    } = Investment::new(project, library);
+
+    let self_consumption = if yield_year_kwh > 0. {
+        consumption_covered / yield_year_kwh
+    } else {
+        0.
+    };
+    let payback_years = price_sum / combined_benefit;
+
+    let storage = input.storage;
+    let discharged = hours.iter().map(|h| h.battery_discharge_kwh).sum::<f32>();
+    let battery_cycles_per_year = if storage.capacity_kwh > 0. {
+        discharged / storage.capacity_kwh
+    } else {
+        0.
+    };
+    let pricing = project.pricing(library);
+    let replacements = project
+        .batteries(library)
+        .flat_map(|b| {
+            replacement_years(
+                b.life_years(battery_cycles_per_year),
+                project.lifetime_years,
+            )
+            .map(|year| {
+                let cost = project.tax.cost(
+                    b.bank_price(&pricing).unwrap_or_default(),
+                    CostCategory::Batteries,
+                );
+                (year, cost.amount())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Analysis {
+        panel_count: project.panels.len(),
+        area_m2: res.area.m2(),
+        kwp,
+        price_sum,
+        price_net,
+        price_gross,
+        efficiency,
+        yield_year_kwh,
+        regular_energy_cost: (project.consumption * project.price_buy).amount(),
+        consumption_covered_kwh: consumption_covered,
+        amount_to_sell_kwh: amount_to_sell,
+        curtailed_kwh: monthly.iter().map(|m| m.curtailed_kwh).sum(),
+        grid_import_kwh,
+        autarky: if consumption_covered + grid_import_kwh > 0. {
+            consumption_covered / (consumption_covered + grid_import_kwh)
+        } else {
+            0.
+        },
+        self_consumption,
+        income_tax: income_tax.amount(),
+        combined_benefit,
+        payback_years,
+        alternative_investment: compound_interest(
+            price_sum,
+            project.interest_rate_deposit,
+            payback_years,
+        ),
+        npv: net_present_value(
+            price_sum,
+            combined_benefit,
+            project.interest_rate_deposit,
+            project.lifetime_years,
+        ) - replacements
+            .iter()
+            .map(|(year, cost)| cost / (1.0 + project.interest_rate_deposit).powf(*year))
+            .sum::<f32>(),
+        battery_cycles_per_year,
+        battery_replacement_year: replacements
+            .iter()
+            .map(|(year, _)| *year)
+            .min_by(f32::total_cmp),
+        battery_replacement_cost: replacements.iter().map(|(_, cost)| cost).sum(),
+        monthly,
+        days: typical_days(&input, &hours),
+    }
+}
+
+impl Analysis {
+    /// The money values in another currency, of which one unit of the project currency
+    /// buys `factor`
+    pub fn converted(mut self, factor: f32) -> Self {
+        for value in [
+            &mut self.price_sum,
+            &mut self.price_net,
+            &mut self.price_gross,
+            &mut self.regular_energy_cost,
+            &mut self.income_tax,
+            &mut self.combined_benefit,
+            &mut self.alternative_investment,
+            &mut self.npv,
+            &mut self.battery_replacement_cost,
+        ] {
+            *value *= factor;
+        }
+        self
+    }
+}
+
+/// Analyses of the scenarios, redone only for those that changed since they were computed
+#[derive(Default)]
+pub struct Analyses {
+    /// The library and the day the entries were computed with
+    library: Option<Library>,
+    day: Option<Date>,
+    entries: Vec<Option<(Project, Analysis)>>,
+}
+
+impl Analyses {
+    /// The analysis of `project`, the scenario at `index`
+    pub fn get(&mut self, index: usize, project: &Project, library: &Library) -> &Analysis {
+        // prices depend on the day unless the project fixes it
+        let day = Date::today();
+        if self.library.as_ref() != Some(library) || self.day != Some(day) {
+            self.library = Some(library.clone());
+            self.day = Some(day);
+            self.entries.clear();
+        }
+        if self.entries.len() <= index {
+            self.entries.resize_with(index + 1, || None);
+        }
+        let entry = &mut self.entries[index];
+        if entry.as_ref().is_some_and(|(p, _)| p != project) {
+            *entry = None;
+        }
+        &entry
+            .get_or_insert_with(|| (project.clone(), analyze(project, library)))
+            .1
+    }
+
+    /// The analyses of all scenarios
+    pub fn all(&mut self, projects: &[Project], library: &Library) -> Vec<&Analysis> {
+        for (i, project) in projects.iter().enumerate() {
+            self.get(i, project, library);
+        }
+        self.entries.truncate(projects.len());
+        self.entries.iter().flatten().map(|(_, a)| a).collect()
+    }
+}
+
+/// What the hourly simulation of `project` starts from
+pub fn simulation_input(project: &Project, library: &Library) -> SimulationInput {
+    let efficiency = efficiency(
+        project.azimuth_from_equator().abs(),
+        project.panel_angle_deg,
+    );
+    SimulationInput {
+        production_year_kwh: project.sum(library).peak_power.kw()
+            * project.yield_kwh_kwp
+            * efficiency,
+        consumption_year_kwh: project.consumption.kwh(),
+        latitude_deg: project.latitude_deg,
+        azimuth_deg: project.azimuth_deg,
+        tilt_deg: project.panel_angle_deg,
+        storage: project.storage(library),
+        feed_in_limit_kw: Some(project.feed_in_limit.kw()).filter(|kw| *kw > 0.),
+    }
+}
+
+/// Years within `lifetime` at which a part lasting `life` years has to be replaced
+pub(crate) fn replacement_years(life: f32, lifetime: f32) -> impl Iterator<Item = f32> {
+    (1..)
+        .map(move |n| n as f32 * life)
+        .take_while(move |year| life > 0. && *year < lifetime)
+}
+
+/// Net present value of an investment returning the same amount every year
+pub fn net_present_value(investment: f32, yearly_return: f32, interest: f32, years: f32) -> f32 {
+    (1..=years.max(0.) as u32)
+        .map(|year| yearly_return / (1.0 + interest).powi(year as i32))
+        .sum::<f32>()
+        - investment
+}
+
+/// simple funciton to determine compound interest as alternative investment
+pub fn compound_interest(start_capital: f32, interest: f32, years: f32) -> f32 {
+    start_capital * (1.0 + interest).powf(years)
+}
+
+#[cfg(test)]
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 0.01
+}
+
+#[test]
+fn analysis() {
+    use crate::tax::{Tax, VatRates};
+
+    // 10 x 400 Wp facing south at 35°, one battery, one inverter
+    let project = Project {
+        panels: vec![0; 10],
+        batteries: vec![0],
+        inverters: vec![1],
+        panel_angle_deg: 35.,
+        ..Default::default()
+    };
+    let a = analyze(&project, &Library::default());
+    assert_eq!(a.panel_count, 10);
+    assert!(close(a.area_m2, 20.04288));
+    assert!(close(a.kwp, 4.));
+    assert!(close(a.price_sum, 10. * 206. + 259.76 + 1090. + 5000.));
+    assert!(close(a.efficiency, 1.));
+    assert!(close(a.yield_year_kwh, 4000.));
+    assert!(close(a.regular_energy_cost, 2500. * 0.4229));
+    // the sun does not shine in the evening, so not all consumption is covered
+    assert!(a.consumption_covered_kwh > 800. && a.consumption_covered_kwh < 2000.);
+    // some energy is lost in the battery
+    assert!(a.consumption_covered_kwh + a.amount_to_sell_kwh < 4000.);
+    assert!((a.consumption_covered_kwh + a.grid_import_kwh - 2500.).abs() < 0.5);
+    assert!(close(a.self_consumption, a.consumption_covered_kwh / 4000.));
+
+    // 19 % VAT on everything and income tax on the feed-in
+    let taxed = analyze(
+        &Project {
+            tax: Tax {
+                vat: VatRates::uniform(0.19),
+                income_tax_rate: 0.3,
+                ..Default::default()
+            },
+            ..project.clone()
+        },
+        &Library::default(),
+    );
+    assert!(close(taxed.price_net, a.price_sum));
+    assert!((taxed.price_sum - a.price_sum * 1.19).abs() < 0.1);
+    assert!(close(taxed.income_tax, a.amount_to_sell_kwh * 0.082 * 0.3));
+    assert!(close(
+        a.combined_benefit - taxed.combined_benefit,
+        taxed.income_tax
+    ));
+
+    // the battery raises self-consumption
+    let without_battery = analyze(
+        &Project {
+            batteries: vec![],
+            ..project
+        },
+        &Library::default(),
+    );
+    let produced = without_battery.consumption_covered_kwh + without_battery.amount_to_sell_kwh;
+    assert!((produced - 4000.).abs() < 0.5);
+    assert!(a.self_consumption > without_battery.self_consumption + 0.05);
+    assert!(close(
+        a.combined_benefit,
+        a.consumption_covered_kwh * 0.4229 + a.amount_to_sell_kwh * 0.082
+    ));
+    assert!(close(a.payback_years, a.price_sum / a.combined_benefit));
+    assert!(close(
+        a.alternative_investment,
+        a.price_sum * 1.042_f32.powf(a.payback_years)
+    ));
+    assert!(a.npv > 0.);
+    // the lead-acid block wears out long before the 20 years are over
+    assert!(a.battery_cycles_per_year > 100.);
+    let life = a.battery_replacement_year.unwrap();
+    assert!(life <= 8.);
+    let replacements = (20. / life).ceil() - 1.;
+    assert!(close(a.battery_replacement_cost, replacements * 259.76));
+    assert_eq!(a.monthly.len(), 12);
+    assert_eq!(a.days.len(), 4);
+}
+
+#[test]
+fn empty_project() {
+    let a = analyze(&Project::default(), &Library::default());
+    assert_eq!(a.yield_year_kwh, 0.);
+    assert_eq!(a.self_consumption, 0.);
+    assert!(a.payback_years.is_infinite());
+    assert!(close(a.npv, -5000.));
+}
+
+#[test]
+fn finance() {
+    assert!(close(compound_interest(100., 0.1, 2.), 121.));
+    assert!(close(net_present_value(100., 110., 0.1, 1.), 0.));
+    assert!(close(net_present_value(100., 50., 0., 3.), 50.));
+}
+
+#[test]
+fn southern_hemisphere() {
+    // Cape Town: the best roof faces north
+    let project = |azimuth_deg| Project {
+        panels: vec![0; 10],
+        panel_angle_deg: 35.,
+        latitude_deg: -33.9,
+        azimuth_deg,
+        ..Default::default()
+    };
+    let north = analyze(&project(0.), &Library::default());
+    let south = analyze(&project(180.), &Library::default());
+    assert!(close(north.efficiency, 1.));
+    assert!(south.efficiency < 0.7);
+    assert!(north.monthly[11].production_kwh > north.monthly[5].production_kwh);
+}
+
+#[test]
+fn cached() {
+    let mut library = Library::default();
+    let mut projects = vec![
+        Project::default(),
+        Project {
+            panels: vec![0; 10],
+            ..Default::default()
+        },
+    ];
+    let mut analyses = Analyses::default();
+    let yields = |analyses: &mut Analyses, projects: &[Project], library: &Library| {
+        analyses
+            .all(projects, library)
+            .iter()
+            .map(|a| a.yield_year_kwh)
+            .collect::<Vec<_>>()
+    };
+    let first = yields(&mut analyses, &projects, &library);
+    assert_eq!(first[0], 0.);
+    assert!(first[1] > 0.);
+    assert_eq!(yields(&mut analyses, &projects, &library), first);
+
+    projects[1].panels.push(0);
+    let more = analyses.get(1, &projects[1], &library).yield_year_kwh;
+    assert!(more > first[1]);
+
+    library.panels[0].peak_power = library.panels[0].peak_power * 2.;
+    assert!(analyses.get(1, &projects[1], &library).yield_year_kwh > more);
+
+    projects.pop();
+    assert_eq!(analyses.all(&projects, &library).len(), 1);
+}
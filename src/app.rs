@@ -1,21 +1,115 @@
-use std::{collections::HashMap, fmt::format, fs::File};
+use std::{
+    fs::File,
+    io::{Cursor, Read},
+};
 
-use egui::{vec2, Rounding};
 use egui_phosphor::regular::*;
-use log::info;
 
 use crate::{
-    components::{compound_interest, Battery, Library, Panel, Project, Inverter},
+    analysis::Analyses,
+    catalogue::MergeStrategy,
+    charts::{daily_profile_chart, day_chart, monthly_chart, pareto_chart, sizing_chart},
+    comparison::comparison_ui,
+    components::{Battery, CostLine, CostUnit, Layer, Library, Project},
+    currency::{currency_combo, exchange_rates_ui},
+    format,
+    import::import,
+    library_browser::{Action, Category, LibraryBrowser},
+    optimizer::{optimize, Constraints, Objective, Optimization},
+    orientation_map::{Compass, OrientationMap},
     panel_orientation::efficiency,
+    quotes::{date_edit, Date, PriceSelection},
+    report::{file_name, ExportFormat, Report},
+    simulation::{month_name, DayKind},
+    sizing::{battery_sizing, Sizing},
+    tax::tax_ui,
+    timeseries::{Sheet, TimeSeries},
     tr,
+    translation::{set_locale, LOCALES},
+    units::Energy,
 };
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
-#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct PVApp {
     library: Library,
-    project: Project,
+    /// All scenarios, e.g. with and without battery
+    projects: Vec<Project>,
+    /// Index of the scenario being edited
+    active: usize,
+    show_comparison: bool,
+    /// Language chosen in the menu, `None` follows the system
+    locale: Option<String>,
+    /// Month shown in the daily profile chart
+    #[serde(skip)]
+    profile_month: usize,
+    /// Day shown in the day chart
+    #[serde(skip)]
+    day_kind: DayKind,
+    /// Library battery whose bank size is swept
+    #[serde(skip)]
+    sizing_battery: Option<usize>,
+    #[serde(skip)]
+    sizing_max_strings: u32,
+    /// Result of the last battery sizing
+    #[serde(skip)]
+    sizing: Option<Sizing>,
+    #[serde(skip)]
+    objective: Objective,
+    #[serde(skip)]
+    constraints: Constraints,
+    /// Result of the last array optimization
+    #[serde(skip)]
+    optimization: Option<Optimization>,
+    #[serde(skip)]
+    browser: LibraryBrowser,
+    #[serde(skip)]
+    analyses: Analyses,
+    /// Library file to load or export a layer of the library
+    catalogue_path: String,
+    /// Layer that loaded and dropped library files are merged into, and that is exported
+    catalogue_layer: Layer,
+    merge_strategy: MergeStrategy,
+    /// Quotes older than this are flagged in the library
+    max_price_age_days: u32,
+    /// Merge `catalogue_path` on start, e.g. a company catalogue on a network share
+    load_catalogue_on_start: bool,
+    /// Outcome of the last library import, one line per file
+    #[serde(skip)]
+    import_status: Vec<String>,
+    /// State from before scenarios existed, moved into `projects` on load
+    #[serde(skip_serializing)]
+    project: Option<Project>,
+}
+
+impl Default for PVApp {
+    fn default() -> Self {
+        Self {
+            library: Default::default(),
+            projects: vec![Project::default()],
+            active: 0,
+            show_comparison: false,
+            locale: None,
+            profile_month: 5,
+            day_kind: DayKind::SummerSolstice,
+            sizing_battery: Some(0),
+            sizing_max_strings: 8,
+            sizing: None,
+            objective: Objective::default(),
+            constraints: Constraints::default(),
+            optimization: None,
+            browser: LibraryBrowser::default(),
+            analyses: Analyses::default(),
+            catalogue_path: String::new(),
+            catalogue_layer: Layer::Company,
+            merge_strategy: MergeStrategy::default(),
+            load_catalogue_on_start: false,
+            max_price_age_days: 90,
+            import_status: vec![],
+            project: None,
+        }
+    }
 }
 
 impl PVApp {
@@ -63,10 +157,327 @@ impl PVApp {
             .into();
             cc.egui_ctx.set_style(style);
 
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let mut app: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            app.library.migrate();
+            if let Some(project) = app.project.take() {
+                app.projects = vec![project];
+            }
+            for project in &mut app.projects {
+                project.migrate();
+            }
+            if app.projects.is_empty() {
+                app.projects.push(Project::default());
+            }
+            // the stored built-in items may be from an older version
+            app.library.rebase(Library::default(), &mut app.projects);
+            app.active = app.active.min(app.projects.len() - 1);
+            set_locale(app.locale.as_deref());
+            #[cfg(not(target_arch = "wasm32"))]
+            if app.load_catalogue_on_start {
+                app.load_catalogue(MergeStrategy::TakeTheirs);
+            }
+            return app;
         }
         Default::default()
     }
+
+    /// Imports CSV files dropped onto the window into the library
+    fn import_dropped(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() {
+            return;
+        }
+        self.import_status.clear();
+        for file in dropped {
+            let name = match &file.path {
+                Some(path) => path.display().to_string(),
+                None => file.name.clone(),
+            };
+            let data: Box<dyn Read> = match (&file.bytes, &file.path) {
+                (Some(bytes), _) => Box::new(Cursor::new(bytes.clone())),
+                (None, Some(path)) => match File::open(path) {
+                    Ok(file) => Box::new(file),
+                    Err(error) => {
+                        self.import_status.push(tr!(
+                            "import_failed",
+                            name = name,
+                            error = error.to_string()
+                        ));
+                        continue;
+                    }
+                },
+                (None, None) => continue,
+            };
+            if name.to_lowercase().ends_with(".json") {
+                self.merge_catalogue(&name, data, self.merge_strategy);
+                continue;
+            }
+            let result = import(&mut self.library, data);
+            self.import_status.push(match result {
+                Ok(report) => tr!(
+                    "import_result",
+                    name = name,
+                    added = report.added,
+                    updated = report.updated,
+                    skipped = report.skipped
+                ),
+                Err(error) => tr!("import_failed", name = name, error = error),
+            });
+        }
+    }
+
+    /// Merges a library file into the library as `catalogue_layer`
+    fn merge_catalogue(&mut self, name: &str, data: impl Read, strategy: MergeStrategy) {
+        let status = match serde_json::from_reader::<_, Library>(data) {
+            Ok(mut theirs) => {
+                theirs.migrate();
+                let report = self.library.merge(theirs, strategy, self.catalogue_layer);
+                tr!(
+                    "merge_result",
+                    name = name,
+                    added = report.added,
+                    replaced = report.replaced,
+                    kept = report.kept
+                )
+            }
+            Err(error) => tr!("import_failed", name = name, error = error.to_string()),
+        };
+        self.import_status.push(status);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_catalogue(&mut self, strategy: MergeStrategy) {
+        let path = self.catalogue_path.clone();
+        match File::open(&path) {
+            Ok(file) => self.merge_catalogue(&path, std::io::BufReader::new(file), strategy),
+            Err(error) => self.import_status.push(tr!(
+                "import_failed",
+                name = path,
+                error = error.to_string()
+            )),
+        }
+    }
+
+    /// Where shared libraries are loaded, merged and exported
+    fn catalogue_ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("catalogue").num_columns(2).show(ui, |ui| {
+            ui.label(tr!("layer"));
+            ui.horizontal(|ui| {
+                for layer in [Layer::Company, Layer::Personal] {
+                    ui.selectable_value(&mut self.catalogue_layer, layer, layer.label());
+                }
+            });
+            ui.end_row();
+            ui.label(tr!("max_price_age"));
+            ui.add(
+                egui::DragValue::new(&mut self.max_price_age_days)
+                    .clamp_range(1..=3650)
+                    .suffix(format!(" {}", tr!("days"))),
+            );
+            ui.end_row();
+            ui.label(tr!("on_conflict"));
+            ui.horizontal(|ui| {
+                for strategy in MergeStrategy::ALL {
+                    ui.selectable_value(&mut self.merge_strategy, strategy, strategy.label());
+                }
+            });
+            ui.end_row();
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.label(tr!("library_file"));
+                ui.text_edit_singleline(&mut self.catalogue_path);
+                ui.end_row();
+                ui.label("");
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("{DOWNLOAD_SIMPLE} {}", tr!("load")))
+                        .clicked()
+                    {
+                        self.import_status.clear();
+                        self.load_catalogue(self.merge_strategy);
+                    }
+                    if ui
+                        .button(format!("{UPLOAD_SIMPLE} {}", tr!("export")))
+                        .clicked()
+                    {
+                        let layer = self.library.layer(self.catalogue_layer);
+                        let written = File::create(&self.catalogue_path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|f| {
+                                serde_json::to_writer_pretty(f, &layer).map_err(|e| e.to_string())
+                            });
+                        if let Err(error) = written {
+                            self.import_status.push(tr!(
+                                "export_failed",
+                                name = self.catalogue_path.clone(),
+                                error = error
+                            ));
+                        }
+                    }
+                    ui.checkbox(&mut self.load_catalogue_on_start, tr!("load_on_start"));
+                });
+                ui.end_row();
+            }
+        });
+    }
+
+    fn scenarios_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            for (id, project) in self.projects.iter().enumerate() {
+                ui.selectable_value(&mut self.active, id, &project.name);
+            }
+            ui.separator();
+            ui.text_edit_singleline(&mut self.projects[self.active].name)
+                .on_hover_text(tr!("scenario_name"));
+            if ui.button(format!("{COPY} {}", tr!("duplicate"))).clicked() {
+                let mut copy = self.projects[self.active].clone();
+                copy.name = copy_name(&copy.name, &self.projects);
+                self.projects.push(copy);
+                self.active = self.projects.len() - 1;
+            }
+            if ui.button(format!("{PLUS} {}", tr!("new"))).clicked() {
+                self.projects.push(Project {
+                    name: tr!("scenario_n", number = self.projects.len() + 1),
+                    ..Default::default()
+                });
+                self.active = self.projects.len() - 1;
+            }
+            if ui
+                .add_enabled(self.projects.len() > 1, egui::Button::new(TRASH_SIMPLE))
+                .clicked()
+            {
+                self.projects.remove(self.active);
+                self.active = self.active.saturating_sub(1);
+            }
+            ui.separator();
+            ui.toggle_value(
+                &mut self.show_comparison,
+                format!("{COLUMNS} {}", tr!("comparison")),
+            );
+            ui.menu_button(format!("{FILE_ARROW_DOWN} {}", tr!("offer")), |ui| {
+                for format in ExportFormat::ALL {
+                    let icon = match format {
+                        ExportFormat::Pdf => FILE_PDF,
+                        ExportFormat::Html => FILE_HTML,
+                        ExportFormat::Markdown => FILE_TEXT,
+                    };
+                    if ui.button(format!("{icon} {}", format.name())).clicked() {
+                        self.export_report(format);
+                        ui.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text(tr!("offer_hint"));
+            ui.menu_button(format!("{TABLE} {}", tr!("time_series")), |ui| {
+                let sheets = [
+                    (
+                        tr!("hourly"),
+                        TimeSeries::hourly as fn(&TimeSeries) -> Sheet,
+                    ),
+                    (tr!("monthly"), TimeSeries::monthly),
+                    (tr!("yearly"), TimeSeries::yearly),
+                ];
+                for (label, sheet) in sheets {
+                    if ui.button(format!("{FILE_CSV} {label}")).clicked() {
+                        self.export_time_series(sheet);
+                        ui.close_menu();
+                    }
+                }
+                #[cfg(feature = "xlsx")]
+                if ui
+                    .button(format!("{FILE_XLS} {}", tr!("workbook")))
+                    .clicked()
+                {
+                    self.export_workbook();
+                    ui.close_menu();
+                }
+            })
+            .response
+            .on_hover_text(tr!("time_series_hint"));
+        });
+    }
+
+    /// Writes the offer for the active scenario
+    fn export_report(&mut self, format: ExportFormat) {
+        let project = &self.projects[self.active];
+        let analysis = self.analyses.get(self.active, project, &self.library);
+        let report = Report::new(project, &self.library, analysis);
+        let name = format!("{}.{}", file_name(project), format.extension());
+        let data = format.render(&report);
+        self.save(name, format.mime_type(), Ok(data));
+    }
+
+    /// Writes the hourly results of the active scenario, or their sums
+    fn export_time_series(&mut self, sheet: fn(&TimeSeries) -> Sheet) {
+        let project = &self.projects[self.active];
+        let sheet = sheet(&TimeSeries::new(project, &self.library));
+        let name = format!("{}_{}.csv", file_name(project), sheet.name);
+        self.save(name, "text/csv", sheet.csv().map(String::into_bytes));
+    }
+
+    #[cfg(feature = "xlsx")]
+    fn export_workbook(&mut self) {
+        let project = &self.projects[self.active];
+        let name = format!("{}.xlsx", file_name(project));
+        let data = TimeSeries::new(project, &self.library).xlsx();
+        self.save(
+            name,
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            data,
+        );
+    }
+
+    /// Writes `data` to the working directory, or downloads it on the web
+    #[cfg_attr(not(target_arch = "wasm32"), allow(unused_variables))]
+    fn save(&mut self, name: String, mime_type: &str, data: Result<Vec<u8>, String>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let written = data.and_then(|data| std::fs::write(&name, data).map_err(|e| e.to_string()));
+        #[cfg(target_arch = "wasm32")]
+        let written = data.and_then(|data| download(&name, mime_type, &data));
+        self.import_status.push(match written {
+            Ok(()) => tr!("report_written", name = name),
+            Err(error) => tr!("export_failed", name = name, error = error),
+        });
+    }
+}
+
+/// `name` with the next "(n)" suffix no scenario has
+fn copy_name(name: &str, projects: &[Project]) -> String {
+    let numbered = name
+        .strip_suffix(')')
+        .and_then(|name| name.rsplit_once(" ("))
+        .filter(|(_, n)| n.parse::<u32>().is_ok());
+    let base = numbered.map_or(name, |(base, _)| base);
+    (2..)
+        .map(|n| format!("{base} ({n})"))
+        .find(|name| projects.iter().all(|p| &p.name != name))
+        .unwrap()
+}
+
+/// Offers `data` as a file download in the browser
+#[cfg(target_arch = "wasm32")]
+fn download(name: &str, mime_type: &str, data: &[u8]) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let error = |e: wasm_bindgen::JsValue| format!("{e:?}");
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+    let mut options = web_sys::BlobPropertyBag::new();
+    options.type_(mime_type);
+    let blob =
+        web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+    let anchor = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("No document")?
+        .create_element("a")
+        .map_err(error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|e| error(e.into()))?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(error)
 }
 
 impl eframe::App for PVApp {
@@ -77,303 +488,675 @@ impl eframe::App for PVApp {
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.import_dropped(ctx);
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 // NOTE: no File->Quit on web pages!
                 let is_web = cfg!(target_arch = "wasm32");
                 if !is_web {
-                    ui.menu_button("File", |ui| {
-                        if ui.button("Quit").clicked() {
+                    ui.menu_button(tr!("file"), |ui| {
+                        if ui.button(tr!("quit")).clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
                     });
                     ui.add_space(16.0);
                 }
+                ui.menu_button(format!("{TRANSLATE} {}", tr!("language")), |ui| {
+                    let mut changed = ui
+                        .radio_value(&mut self.locale, None, tr!("system_language"))
+                        .clicked();
+                    for (locale, name) in LOCALES {
+                        changed |= ui
+                            .radio_value(&mut self.locale, Some(locale.to_string()), name)
+                            .clicked();
+                    }
+                    if changed {
+                        set_locale(self.locale.as_deref());
+                        ui.close_menu();
+                    }
+                });
+                ui.add_space(16.0);
                 egui::widgets::global_dark_light_mode_buttons(ui);
             });
+            self.scenarios_ui(ui);
         });
 
-        egui::SidePanel::right("library").show(ctx, |ui| {
-            ui.collapsing(format!("{RECTANGLE} Panels"), |ui| {
-                let mut delete: Option<usize> = None;
+        egui::Window::new(tr!("comparison"))
+            .open(&mut self.show_comparison)
+            .show(ctx, |ui| {
+                // all in the currency of the active scenario
+                let currency = &self.projects[self.active].currency;
+                let analyses = self.analyses.all(&self.projects, &self.library);
+                comparison_ui(ui, &self.projects, &analyses, &self.library, currency);
+            });
 
-                for (id, module) in self.library.panels.iter_mut().enumerate() {
-                    ui.push_id(id, |ui| {
-                        ui.add(module);
-                    });
-                    if ui.button(tr!("Hinzufuegen")).clicked() {
-                        self.project.panels.push(id);
-                    }
-                    if ui.button(TRASH_SIMPLE).clicked() {
-                        delete = Some(id);
-                    }
-                }
+        egui::SidePanel::right("library").show(ctx, |ui| {
+            ui.label(format!("{FILE_CSV} {}", tr!("import_hint")));
+            ui.collapsing(format!("{STACK} {}", tr!("catalogues")), |ui| {
+                self.catalogue_ui(ui);
+            });
+            ui.collapsing(
+                format!("{CURRENCY_CIRCLE_DOLLAR} {}", tr!("exchange_rates")),
+                |ui| exchange_rates_ui(ui, &mut self.library.exchange_rates),
+            );
+            for line in &self.import_status {
+                ui.label(line);
+            }
+            ui.separator();
 
-                if let Some(id) = delete {
-                    self.library.panels.remove(id);
+            let project = &mut self.projects[self.active];
+            self.browser.pricing = project.pricing(&self.library);
+            self.browser.max_price_age_days = self.max_price_age_days;
+            match self.browser.ui(ui, &mut self.library) {
+                Some(Action::Add(Category::Panels, id)) => project.panels.push(id),
+                Some(Action::Add(Category::Inverters, id)) => project.inverters.push(id),
+                Some(Action::Add(Category::Batteries, id)) => project.batteries.push(id),
+                Some(Action::Add(Category::CostItems, id)) => project.cost_items.push(CostLine {
+                    item: id,
+                    quantity: 1.,
+                }),
+                Some(Action::Delete(category, id)) => {
+                    category.remove(
+                        id,
+                        &mut self.library,
+                        &mut self.projects,
+                        &mut self.sizing_battery,
+                    );
                 }
+                None => {}
+            }
+        });
 
-                ui.separator();
+        let project = &mut self.projects[self.active];
 
-                if ui
-                    .add(
-                        egui::Button::new(PLUS).rounding(Rounding::same(20.)), // .fill(ui.style().visuals.hyperlink_color)
-                                                                               // .min_size(vec2(30., 30.)),
-                    )
-                    .clicked()
-                {
-                    self.library.panels.push(Panel::default());
+        egui::TopBottomPanel::bottom("footer").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(tr!("reset")).clicked() {
+                    *project = Project {
+                        name: project.name.clone(),
+                        ..Default::default()
+                    };
                 }
+                ui.label(tr!("disclaimer"));
+                ui.add(egui::github_link_file!(
+                    "https://github.com/woelper/pvcalc",
+                    tr!("source_code")
+                ));
             });
+        });
 
-            ui.collapsing(tr!("{RECTANGLE} Inverter"), |ui| {
-                let mut delete: Option<usize> = None;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let analysis = self.analyses.get(self.active, project, &self.library);
 
-                for (id, inverter) in self.library.inverters.iter_mut().enumerate() {
-                    ui.push_id(id, |ui| {
-                        ui.add(inverter);
+                ui.collapsing(format!("{USER} {}", tr!("customer")), |ui| {
+                    let customer = &mut project.customer;
+                    egui::Grid::new("customer").num_columns(2).show(ui, |ui| {
+                        ui.label(tr!("name"));
+                        ui.text_edit_singleline(&mut customer.name);
+                        ui.end_row();
+                        ui.label(tr!("address"));
+                        ui.add(egui::TextEdit::multiline(&mut customer.address).desired_rows(2));
+                        ui.end_row();
+                        ui.label(tr!("email"));
+                        ui.text_edit_singleline(&mut customer.email);
+                        ui.end_row();
+                        ui.label(tr!("phone"));
+                        ui.text_edit_singleline(&mut customer.phone);
+                        ui.end_row();
                     });
-                    if ui.button(tr!("Hinzufuegen")).clicked() {
-                        self.project.inverters.push(id);
-                    }
-                    if ui.button(TRASH_SIMPLE).clicked() {
-                        delete = Some(id);
-                    }
-                }
+                });
 
-                if let Some(id) = delete {
-                    self.library.inverters.remove(id);
+                ui.heading(tr!("system"));
+                ui.label(tr!(
+                    "panels_installed",
+                    count = analysis.panel_count,
+                    area = format::area(analysis.area_m2)
+                ));
+                ui.label(tr!("peak_power_value", kwp = format::kwp(analysis.kwp)));
+                let storage = project.storage(&self.library);
+                if storage.capacity_kwh > 0. {
+                    ui.label(tr!(
+                        "storage_value",
+                        kwh = format::number(storage.capacity_kwh, 1)
+                    ));
                 }
 
-                ui.separator();
-
-                if ui
-                    .add(
-                        egui::Button::new(PLUS).rounding(Rounding::same(20.)), // .fill(ui.style().visuals.hyperlink_color)
-                                                                               // .min_size(vec2(30., 30.)),
-                    )
-                    .clicked()
-                {
-                    self.library.inverters.push(Inverter::default());
+                ui.horizontal(|ui| {
+                    ui.label(tr!("currency"));
+                    currency_combo(
+                        ui,
+                        "project_currency",
+                        &mut project.currency,
+                        &self.library.exchange_rates,
+                    );
+                });
+                let missing = project.missing_rates(&self.library);
+                if !missing.is_empty() {
+                    let currencies = missing.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        tr!(
+                            "missing_rates",
+                            currencies = currencies.join(", "),
+                            currency = project.currency
+                        ),
+                    );
                 }
-            });
-
-            ui.collapsing(tr!("{BATTERY_FULL} Batterien"), |ui| {
-                let mut delete: Option<usize> = None;
 
-                for (id, battery) in self.library.batteries.iter_mut().enumerate() {
-                    ui.push_id(id, |ui| {
-                        ui.add(battery);
-                    });
-                    if ui.button(tr!("Hinzufuegen")).clicked() {
-                        self.project.batteries.push(id);
+                ui.horizontal(|ui| {
+                    ui.label(tr!("component_prices"));
+                    for selection in PriceSelection::ALL {
+                        ui.radio_value(&mut project.price_selection, selection, selection.label());
                     }
-                    if ui.button(TRASH_SIMPLE).clicked() {
-                        delete = Some(id);
+                });
+                ui.horizontal(|ui| {
+                    let mut fixed = project.price_date.is_some();
+                    if ui
+                        .checkbox(&mut fixed, tr!("price_date"))
+                        .on_hover_text(tr!("price_date_hint"))
+                        .changed()
+                    {
+                        project.price_date = fixed.then(Date::today);
                     }
-                }
+                    if let Some(date) = &mut project.price_date {
+                        date_edit(ui, date);
+                    }
+                });
 
-                if let Some(id) = delete {
-                    self.library.panels.remove(id);
-                }
+                ui.horizontal(|ui| {
+                    ui.label(tr!("installation_cost_panels"));
+                    ui.add(
+                        project
+                            .price_installation_panels
+                            .drag_in(&project.currency)
+                            .speed(0.1),
+                    );
+                });
 
-                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(tr!("installation_cost_electrics"));
+                    ui.add(
+                        project
+                            .price_installation_electricity
+                            .drag_in(&project.currency)
+                            .speed(0.1),
+                    );
+                });
 
-                if ui
-                    .add(
-                        egui::Button::new(PLUS).rounding(Rounding::same(20.)), // .fill(ui.style().visuals.hyperlink_color)
-                                                                               // .min_size(vec2(30., 30.)),
-                    )
-                    .clicked()
-                {
-                    self.library.batteries.push(Battery::default());
-                }
-            });
+                ui.collapsing(format!("{PERCENT} {}", tr!("taxes")), |ui| {
+                    tax_ui(ui, &mut project.tax);
+                });
 
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                if ui.button("Save").clicked() {
-                    _ = serde_json::to_writer_pretty(
-                        File::create("lib.json").unwrap(),
-                        &self.library,
-                    );
-                }
-            }
-        });
+                ui.collapsing(format!("{WRENCH} {}", tr!("cost_items")), |ui| {
+                    if project.cost_items.is_empty() {
+                        ui.label(tr!("cost_items_hint"));
+                    }
+                    let mut delete = None;
+                    egui::Grid::new("cost_lines").num_columns(4).show(ui, |ui| {
+                        for (i, line) in project.cost_items.iter_mut().enumerate() {
+                            let Some(item) = self.library.cost_items.get(line.item) else {
+                                continue;
+                            };
+                            ui.label(format!("{} {}", item.brand, item.model).trim());
+                            if item.unit == CostUnit::Flat {
+                                ui.label("");
+                            } else {
+                                ui.add(
+                                    egui::DragValue::new(&mut line.quantity)
+                                        .clamp_range(0.0..=f32::MAX)
+                                        .speed(0.1),
+                                );
+                            }
+                            ui.label(item.unit.label());
+                            if ui.small_button(TRASH_SIMPLE).clicked() {
+                                delete = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(i) = delete {
+                        project.cost_items.remove(i);
+                    }
+                });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let res = self.project.sum(&self.library);
+                ui.horizontal(|ui| {
+                    ui.label(tr!("roof_area"));
+                    ui.add(project.roof_area.drag_m2().speed(0.1))
+                        .on_hover_text(tr!("zero_unknown"));
+                });
 
-            ui.heading("Anlage");
-            ui.label(tr!(
-                "{} Panels installiert auf {} qm ",
-                self.project.panels.len(),
-                res.area_sum / 10000.
-            ));
-            ui.label(tr!("Leistung Peak: {:?} kWp", res.energy_sum_wp));
+                ui.label(tr!(
+                    "total_cost_value",
+                    net = format::money(analysis.price_net, &project.currency),
+                    gross = format::money(analysis.price_gross, &project.currency)
+                ));
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Installationskosten Panels"));
-                ui.add(
-                    egui::DragValue::new(&mut self.project.price_installation_panels)
-                        .speed(0.1)
-                        .suffix(" €"),
+                ui.collapsing(
+                    format!("{LIST_BULLETS} {}", tr!("bill_of_materials")),
+                    |ui| {
+                        egui::Grid::new("bill_of_materials")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.strong(tr!("description"));
+                                ui.strong(tr!("quantity"));
+                                ui.strong(tr!("unit_price"));
+                                ui.strong(tr!("total"));
+                                ui.end_row();
+                                let lines = project.bill_of_materials(&self.library);
+                                for line in &lines {
+                                    ui.label(&line.description);
+                                    ui.label(format!(
+                                        "{} {}",
+                                        format::number(line.quantity, 1),
+                                        line.unit
+                                    ));
+                                    ui.label(format::money(
+                                        line.unit_price.amount(),
+                                        &project.currency,
+                                    ));
+                                    ui.label(format::money(line.total.amount(), &project.currency));
+                                    ui.end_row();
+                                }
+                                let total = |ui: &mut egui::Ui, label: String, value: f32| {
+                                    ui.strong(label);
+                                    ui.label("");
+                                    ui.label("");
+                                    ui.strong(format::money(value, &project.currency));
+                                    ui.end_row();
+                                };
+                                total(ui, tr!("net_total"), analysis.price_net);
+                                for group in project.tax.vat_groups(&lines) {
+                                    let percent = format::percent(group.rate);
+                                    total(
+                                        ui,
+                                        tr!("vat_value", percent = percent),
+                                        group.vat.amount(),
+                                    );
+                                }
+                                total(ui, tr!("gross_total"), analysis.price_gross);
+                            });
+                    },
                 );
-            });
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Installationskosten Elektrik"));
-                ui.add(
-                    egui::DragValue::new(&mut self.project.price_installation_electricity)
-                        .speed(0.1)
-                        .suffix(" €"),
-                );
-            });
+                ui.horizontal(|ui| {
+                    ui.label(tr!("global_irradiation"));
+                    ui.add(egui::DragValue::new(&mut project.yield_kwh_kwp));
+                    egui::ComboBox::from_label("")
+                        .selected_text(format!("{CLOUD_SUN} {}", tr!("exposure")))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut project.yield_kwh_kwp,
+                                1000.,
+                                tr!("exposure_sunny"),
+                            );
+                            ui.selectable_value(
+                                &mut project.yield_kwh_kwp,
+                                400.,
+                                tr!("exposure_light_clouds"),
+                            );
+                            ui.selectable_value(
+                                &mut project.yield_kwh_kwp,
+                                150.,
+                                tr!("exposure_heavy_clouds"),
+                            );
+                            ui.selectable_value(
+                                &mut project.yield_kwh_kwp,
+                                50.,
+                                tr!("exposure_rain"),
+                            );
+                        });
+                });
 
-            ui.label(tr!("Gesamtkosten: {:?} €", res.price_sum));
+                ui.horizontal(|ui| {
+                    ui.label(tr!("orientation"));
+                    ui.add(Compass {
+                        azimuth_deg: &mut project.azimuth_deg,
+                    });
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut project.azimuth_deg)
+                                .speed(0.5)
+                                .suffix("°"),
+                        )
+                        .changed()
+                    {
+                        project.azimuth_deg = project.azimuth_deg.rem_euclid(360.);
+                    }
+                });
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Globalstrahlung"));
-                ui.add(egui::DragValue::new(&mut self.project.yield_kwh_kwp));
-                egui::ComboBox::from_label("")
-                    .selected_text(tr!("{CLOUD_SUN} Exposure"))
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.project.yield_kwh_kwp, 1000., "Sunny");
-                        ui.selectable_value(&mut self.project.yield_kwh_kwp, 400., "Light clouds");
-                        ui.selectable_value(&mut self.project.yield_kwh_kwp, 150., "Heavy clouds");
-                        ui.selectable_value(&mut self.project.yield_kwh_kwp, 50., "Rain");
+                ui.horizontal(|ui| {
+                    ui.label(tr!("inclination"));
+                    ui.add(
+                        egui::DragValue::new(&mut project.panel_angle_deg)
+                            .clamp_range(0.0..=90.)
+                            .speed(0.1)
+                            .suffix("°"),
+                    );
+                });
+                ui.label(tr!(
+                    "efficiency_value",
+                    efficiency = format::percent(analysis.efficiency)
+                ));
+                ui.collapsing(tr!("orientation_map"), |ui| {
+                    ui.small(tr!("orientation_map_reference"));
+                    ui.add(OrientationMap {
+                        azimuth_deg: &mut project.azimuth_deg,
+                        tilt_deg: &mut project.panel_angle_deg,
+                        latitude_deg: project.latitude_deg,
+                        efficiency: &|deviation, tilt| efficiency(deviation.abs(), tilt),
                     });
-            });
+                });
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Ausrichtung (Abweichung von Sueden)"));
-                ui.add(
-                    egui::DragValue::new(&mut self.project.panel_orientation)
-                        .clamp_range(0.0..=180.)
-                        .speed(0.1)
-                        .suffix(" Grad"),
-                );
-            });
+                ui.horizontal(|ui| {
+                    ui.label(tr!("latitude"));
+                    ui.add(
+                        egui::DragValue::new(&mut project.latitude_deg)
+                            .clamp_range(-66.0..=66.)
+                            .speed(0.1)
+                            .suffix("°"),
+                    );
+                });
+                ui.separator();
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Neigungswinkel (0 = flach)"));
-                ui.add(
-                    egui::DragValue::new(&mut self.project.panel_angle_deg)
-                        .clamp_range(0.0..=90.)
-                        .speed(0.1)
-                        .suffix(" Grad"),
-                );
-            });
-            ui.label(tr!(
-                "Effizienz: {}",
-                efficiency(self.project.panel_orientation, self.project.panel_angle_deg)
-            ));
-            ui.separator();
+                ui.heading(tr!("market"));
+                ui.horizontal(|ui| {
+                    ui.label(tr!("price_per_kwh"));
+                    ui.add(project.price_buy.drag_in(&project.currency).speed(0.01));
+                });
 
-            ui.heading("Markt");
-            ui.horizontal(|ui| {
-                ui.label(tr!("Preis pro kWh"));
-                ui.add(
-                    egui::DragValue::new(&mut self.project.price_kwh_eur_buy)
-                        .speed(0.01)
-                        .suffix(" €"),
-                );
-            });
+                ui.horizontal(|ui| {
+                    ui.label(tr!("feed_in_tariff"));
+                    ui.add(project.price_sell.drag_in(&project.currency).speed(0.01));
+                });
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Einspeiseverguetung pro kWh"));
-                ui.add(
-                    egui::DragValue::new(&mut self.project.price_kwh_eur_sell)
-                        .speed(0.01)
-                        .suffix(" €"),
-                );
-            });
+                ui.horizontal(|ui| {
+                    ui.label(tr!("feed_in_limit"));
+                    ui.add(project.feed_in_limit.drag_kw().speed(0.1))
+                        .on_hover_text(tr!("zero_unlimited"));
+                });
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Zins Festgeld"));
-                ui.add(
-                    egui::DragValue::new(&mut self.project.interest_rate_deposit)
-                        .speed(0.01)
-                        .suffix(" %/100"),
-                );
-            });
+                ui.horizontal(|ui| {
+                    ui.label(tr!("deposit_interest"));
+                    ui.add(
+                        egui::DragValue::new(&mut project.interest_rate_deposit)
+                            .speed(0.01)
+                            .suffix(" %/100"),
+                    );
+                });
 
-            ui.horizontal(|ui| {
-                ui.label(tr!("Verbrauch kWh/Jahr"));
-                ui.add(egui::DragValue::new(&mut self.project.consumption_kwh).suffix(" kWh"));
-                egui::ComboBox::from_id_source("v")
-                    .selected_text(tr!("{CLOUD_SUN} Verbrauch"))
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(
-                            &mut self.project.consumption_kwh,
-                            1500.,
-                            tr!("1 Person"),
-                        );
-                        ui.selectable_value(
-                            &mut self.project.consumption_kwh,
-                            2500.,
-                            tr!("2 Personen"),
-                        );
-                        ui.selectable_value(
-                            &mut self.project.consumption_kwh,
-                            3500.,
-                            tr!("3 Personen"),
-                        );
-                        ui.selectable_value(
-                            &mut self.project.consumption_kwh,
-                            4250.,
-                            tr!("4 Personen"),
-                        );
-                    });
-            });
+                ui.horizontal(|ui| {
+                    ui.label(tr!("lifetime"));
+                    ui.add(
+                        egui::DragValue::new(&mut project.lifetime_years)
+                            .clamp_range(1.0..=40.)
+                            .speed(0.1)
+                            .suffix(tr!("years_suffix")),
+                    );
+                });
 
-            ui.heading(tr!("Analyse"));
-            ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(tr!("consumption_per_year"));
+                    ui.add(project.consumption.drag_kwh());
+                    egui::ComboBox::from_id_source("v")
+                        .selected_text(format!("{CLOUD_SUN} {}", tr!("consumption")))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut project.consumption,
+                                Energy::from_kwh(1500.),
+                                tr!("one_person"),
+                            );
+                            ui.selectable_value(
+                                &mut project.consumption,
+                                Energy::from_kwh(2500.),
+                                tr!("persons", count = 2),
+                            );
+                            ui.selectable_value(
+                                &mut project.consumption,
+                                Energy::from_kwh(3500.),
+                                tr!("persons", count = 3),
+                            );
+                            ui.selectable_value(
+                                &mut project.consumption,
+                                Energy::from_kwh(4250.),
+                                tr!("persons", count = 4),
+                            );
+                        });
+                });
 
-            let efficiency =
-                efficiency(self.project.panel_orientation, self.project.panel_angle_deg);
-            let yield_year_kwh =
-                (res.energy_sum_wp / 1000. * self.project.yield_kwh_kwp) * efficiency;
-            ui.label(tr!("Ertrag pro Jahr: {:?} kWh", yield_year_kwh));
-
-            let regular_energy_cost = self.project.consumption_kwh * self.project.price_kwh_eur_buy;
-            ui.label(tr!(
-                "Stromkosten pro Jahr bei ausschliesslicher Netznutzung: {regular_energy_cost} €"
-            ));
-
-            // how much of the power we generate can be self-used
-            let consumption_covered = yield_year_kwh.min(self.project.consumption_kwh);
-            let amount_to_sell = (yield_year_kwh - self.project.consumption_kwh).max(0.0);
-
-            let combined_benefit = consumption_covered * self.project.price_kwh_eur_buy
-                + amount_to_sell * self.project.price_kwh_eur_sell;
-
-            ui.label(tr!("Gesamteinnahmen {}", combined_benefit));
-            ui.label(tr!(
-                "Amortisiert nach {:.1} Jahren",
-                res.price_sum / combined_benefit
-            ));
-            let alternative_investment = compound_interest(
-                res.price_sum,
-                self.project.interest_rate_deposit,
-                res.price_sum / combined_benefit,
-            );
-            ui.label(tr!(
-                "Alternativ: Investitionssumme verzinsen: {:.0}€ ({:.0}€ mehr)",
-                alternative_investment,
-                alternative_investment - res.price_sum
-            ));
-
-            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-                if ui.button("Reset").clicked() {
-                    self.project = Default::default();
+                ui.heading(tr!("analysis"));
+                ui.separator();
+
+                // inputs above may have changed
+                let analysis = self.analyses.get(self.active, project, &self.library);
+                ui.label(tr!(
+                    "yield_value",
+                    kwh = format::kwh(analysis.yield_year_kwh)
+                ));
+                if analysis.curtailed_kwh > 0. {
+                    ui.label(tr!(
+                        "curtailed_value",
+                        kwh = format::kwh(analysis.curtailed_kwh)
+                    ));
                 }
-                ui.label(tr!("Alle Angeben ohne Gewaehr!"));
-                ui.add(egui::github_link_file!(
-                    "https://github.com/woelper/pvcalc",
-                    "Source code."
+                ui.label(tr!(
+                    "grid_only_cost",
+                    cost = format::money(analysis.regular_energy_cost, &project.currency)
+                ));
+                ui.label(tr!(
+                    "self_consumption_value",
+                    percent = format::percent(analysis.self_consumption)
+                ));
+                ui.label(tr!(
+                    "total_income",
+                    income = format::money(analysis.combined_benefit, &project.currency)
                 ));
+                ui.label(tr!(
+                    "payback_after",
+                    years = format::years(analysis.payback_years)
+                ));
+                ui.label(tr!(
+                    "alternative_investment",
+                    amount = format::money(analysis.alternative_investment, &project.currency),
+                    gain = format::money(
+                        analysis.alternative_investment - analysis.price_sum,
+                        &project.currency
+                    )
+                ));
+                ui.label(tr!(
+                    "npv_after",
+                    years = format::number(project.lifetime_years, 0),
+                    npv = format::money(analysis.npv, &project.currency)
+                ));
+                if analysis.battery_cycles_per_year > 0. {
+                    ui.label(tr!(
+                        "battery_cycles",
+                        cycles = format::number(analysis.battery_cycles_per_year, 0)
+                    ));
+                    match analysis.battery_replacement_year {
+                        Some(year) => ui.label(tr!(
+                            "battery_replacement",
+                            year = format::years(year),
+                            cost =
+                                format::money(analysis.battery_replacement_cost, &project.currency)
+                        )),
+                        None => ui.label(tr!("battery_lasts")),
+                    };
+                }
+
+                ui.separator();
+                ui.heading(tr!("monthly_overview"));
+                monthly_chart(ui, &analysis.monthly);
+
+                ui.horizontal(|ui| {
+                    ui.label(tr!("average_day_in"));
+                    egui::ComboBox::from_id_source("profile_month")
+                        .selected_text(month_name(self.profile_month))
+                        .show_ui(ui, |ui| {
+                            for m in 0..12 {
+                                ui.selectable_value(&mut self.profile_month, m, month_name(m));
+                            }
+                        });
+                });
+                if let Some(month) = analysis.monthly.get(self.profile_month) {
+                    daily_profile_chart(ui, month);
+                }
+
+                ui.separator();
+                ui.heading(tr!("daily_course"));
+                ui.horizontal(|ui| {
+                    for kind in DayKind::ALL {
+                        let response = ui.selectable_value(&mut self.day_kind, kind, kind.label());
+                        if matches!(kind, DayKind::Best | DayKind::Worst) {
+                            response.on_hover_text(tr!("synthetic_weather_hint"));
+                        }
+                    }
+                });
+                if let Some(day) = analysis.days.iter().find(|d| d.kind == self.day_kind) {
+                    day_chart(ui, day);
+                }
+
+                ui.separator();
+                ui.heading(tr!("battery_sizing"));
+                ui.horizontal(|ui| {
+                    let name = |b: &Battery| format!("{} {}", b.brand, b.model);
+                    egui::ComboBox::from_id_source("sizing_battery")
+                        .selected_text(
+                            self.sizing_battery
+                                .and_then(|id| self.library.batteries.get(id))
+                                .map(name)
+                                .unwrap_or_default(),
+                        )
+                        .show_ui(ui, |ui| {
+                            for (id, battery) in self.library.batteries.iter().enumerate() {
+                                ui.selectable_value(
+                                    &mut self.sizing_battery,
+                                    Some(id),
+                                    name(battery),
+                                );
+                            }
+                        });
+                    ui.label(tr!("up_to_strings"));
+                    ui.add(egui::DragValue::new(&mut self.sizing_max_strings).clamp_range(1..=32));
+                    let battery = self
+                        .sizing_battery
+                        .and_then(|id| self.library.batteries.get(id));
+                    if let Some(battery) = battery {
+                        if ui.button(tr!("calculate")).clicked() {
+                            self.sizing = Some(battery_sizing(
+                                project,
+                                &self.library,
+                                battery,
+                                self.sizing_max_strings,
+                            ));
+                        }
+                    }
+                });
+                if let Some(sizing) = &self.sizing {
+                    if let (Some(optimal), Some(knee)) = (sizing.optimal(), sizing.knee()) {
+                        ui.label(tr!(
+                            "sizing_optimum",
+                            kwh = format::number(optimal.capacity_kwh, 1),
+                            npv = format::money(optimal.npv, &project.currency)
+                        ));
+                        ui.label(tr!(
+                            "sizing_knee",
+                            kwh = format::number(knee.capacity_kwh, 1),
+                            autarky = format::percent(knee.autarky)
+                        ));
+                    }
+                    sizing_chart(ui, sizing, &project.currency);
+                }
+
+                ui.separator();
+                ui.heading(tr!("array_optimizer"));
+                ui.horizontal(|ui| {
+                    for objective in Objective::ALL {
+                        ui.selectable_value(&mut self.objective, objective, objective.label());
+                    }
+                    ui.label(tr!("budget"));
+                    ui.add(
+                        self.constraints
+                            .budget
+                            .drag_in(&project.currency)
+                            .speed(10.),
+                    )
+                    .on_hover_text(tr!("zero_unlimited"));
+                    if ui.button(tr!("calculate")).clicked() {
+                        self.optimization = Some(optimize(
+                            project,
+                            &self.library,
+                            self.objective,
+                            self.constraints,
+                        ));
+                    }
+                });
+                if let Some(optimization) = &self.optimization {
+                    let inverter_name = |inverter: Option<usize>| {
+                        inverter
+                            .and_then(|i| self.library.inverters.get(i))
+                            .map(|i| format!("{} {}", i.brand, i.model))
+                            .unwrap_or_else(|| "–".into())
+                    };
+                    match optimization.best() {
+                        Some(best) => {
+                            ui.horizontal(|ui| {
+                                ui.label(tr!(
+                                    "best_configuration_value",
+                                    count = best.panels,
+                                    kwp = format::kwp(best.kwp),
+                                    inverter = inverter_name(best.inverter),
+                                    cost = format::money(best.price, &project.currency),
+                                    npv = format::money(best.npv, &project.currency),
+                                    years = format::years(best.payback_years)
+                                ));
+                                if ui.button(tr!("apply")).clicked() {
+                                    best.apply(project, optimization.panel);
+                                }
+                            });
+                        }
+                        None => {
+                            ui.label(tr!("no_configuration"));
+                        }
+                    }
+                    pareto_chart(ui, optimization, &project.currency);
+                    ui.collapsing(tr!("pareto_front"), |ui| {
+                        egui::Grid::new("pareto").striped(true).show(ui, |ui| {
+                            for i in &optimization.pareto {
+                                let c = &optimization.candidates[*i];
+                                ui.label(tr!("panel_count", count = c.panels));
+                                ui.label(inverter_name(c.inverter));
+                                ui.label(format::money(c.price, &project.currency));
+                                ui.label(format::percent(c.autarky));
+                                ui.label(format::money(c.npv, &project.currency));
+                                if ui.button(tr!("apply")).clicked() {
+                                    c.apply(project, optimization.panel);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
             });
         });
     }
 }
+
+#[test]
+fn copy_names() {
+    let projects = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| Project {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(copy_name("X", &projects(&["X"])), "X (2)");
+    assert_eq!(copy_name("X (2)", &projects(&["X", "X (2)"])), "X (3)");
+    assert_eq!(copy_name("X", &projects(&["X", "X (2)", "X (3)"])), "X (4)");
+    assert_eq!(copy_name("Y (a)", &projects(&["Y (a)"])), "Y (a) (2)");
+}
@@ -0,0 +1,502 @@
+//! Sharing libraries: items of one library are merged into another by brand and model, and
+//! the built-in library, a company catalogue and personal changes are stacked as layers. An
+//! item of a higher layer keeps the one it overrides, so reverting the override shows the
+//! lower one again. On start, the built-in layer is replaced with the library of the running
+//! version.
+//!
+//! Merging only replaces items in place or appends them, so the library indices projects
+//! refer to stay valid.
+
+use std::collections::HashMap;
+
+use crate::{
+    components::{Battery, CostItem, CostLine, Inverter, Layer, Library, Panel, Project},
+    currency::Currency,
+    quotes::{net, Pricing, Quote},
+    tr,
+    units::Money,
+};
+
+/// Brand and model, ignoring case and surrounding space
+pub(crate) fn key(brand: &str, model: &str) -> (String, String) {
+    (brand.trim().to_lowercase(), model.trim().to_lowercase())
+}
+
+/// What panels, inverters, batteries and cost items have in common
+pub(crate) trait Product: Sized {
+    fn key(&self) -> (String, String);
+    fn model(&mut self) -> &mut String;
+    fn list_price(&self) -> Money;
+    /// Of the list price
+    fn currency(&self) -> &Currency;
+    /// 0-1, the VAT rate the list price and the quotes include, 0 if they are net
+    fn included_vat(&self) -> f32;
+    fn quotes(&self) -> &[Quote];
+    fn quotes_mut(&mut self) -> &mut Vec<Quote>;
+    /// Takes the list price of `other`, with its currency and VAT
+    fn take_list_price(&mut self, other: &Self);
+    fn layer(&mut self) -> &mut Layer;
+    /// The item of a lower layer this one overrides, shown again when the override is reverted
+    fn overridden(&mut self) -> &mut Option<Box<Self>>;
+
+    /// List price, or the price of the quote chosen by `pricing`, without VAT. `None` without
+    /// exchange rate.
+    fn price(&self, pricing: &Pricing) -> Option<Money> {
+        let price = pricing.price(self.list_price(), self.currency(), self.quotes())?;
+        Some(net(price, self.included_vat()))
+    }
+}
+
+macro_rules! product {
+    ($($item:ty),*) => {
+        $(impl Product for $item {
+            fn key(&self) -> (String, String) {
+                key(&self.brand, &self.model)
+            }
+            fn model(&mut self) -> &mut String {
+                &mut self.model
+            }
+            fn list_price(&self) -> Money {
+                self.price
+            }
+            fn currency(&self) -> &Currency {
+                &self.currency
+            }
+            fn included_vat(&self) -> f32 {
+                if self.price_gross {
+                    self.vat_rate
+                } else {
+                    0.
+                }
+            }
+            fn quotes(&self) -> &[Quote] {
+                &self.quotes
+            }
+            fn quotes_mut(&mut self) -> &mut Vec<Quote> {
+                &mut self.quotes
+            }
+            fn take_list_price(&mut self, other: &Self) {
+                self.price = other.price;
+                self.price_gross = other.price_gross;
+                self.vat_rate = other.vat_rate;
+                self.currency = other.currency.clone();
+            }
+            fn layer(&mut self) -> &mut Layer {
+                &mut self.layer
+            }
+            fn overridden(&mut self) -> &mut Option<Box<Self>> {
+                &mut self.overridden
+            }
+        })*
+    };
+}
+
+product!(Panel, Inverter, Battery, CostItem);
+
+pub(crate) fn index_of<T: Product>(items: &[T]) -> HashMap<(String, String), usize> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.key(), i))
+        .collect()
+}
+
+/// What to do with an item that both libraries have
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum MergeStrategy {
+    KeepMine,
+    #[default]
+    TakeTheirs,
+    /// Add theirs with a numbered model name
+    KeepBoth,
+}
+
+impl MergeStrategy {
+    pub const ALL: [MergeStrategy; 3] = [
+        MergeStrategy::KeepMine,
+        MergeStrategy::TakeTheirs,
+        MergeStrategy::KeepBoth,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            MergeStrategy::KeepMine => tr!("keep_mine"),
+            MergeStrategy::TakeTheirs => tr!("take_theirs"),
+            MergeStrategy::KeepBoth => tr!("keep_both"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MergeReport {
+    pub added: usize,
+    pub replaced: usize,
+    /// Conflicts where our item was kept
+    pub kept: usize,
+}
+
+/// Puts `item` into the stack of `top`: replacing the item of the same layer, over the items
+/// of lower layers and under the items of higher ones
+pub(crate) fn stack<T: Product>(top: &mut T, mut item: T) {
+    let layer = *item.layer();
+    if layer < *top.layer() {
+        match top.overridden() {
+            Some(below) => stack(below.as_mut(), item),
+            None => *top.overridden() = Some(Box::new(item)),
+        }
+        return;
+    }
+    let mut replaced = std::mem::replace(top, item);
+    *top.overridden() = if *replaced.layer() == layer {
+        replaced.overridden().take()
+    } else {
+        Some(Box::new(replaced))
+    };
+}
+
+/// Drops the top of the stack, showing the item it overrides. `false` if it overrides none.
+pub(crate) fn revert<T: Product>(item: &mut T) -> bool {
+    match item.overridden().take() {
+        Some(below) => {
+            *item = *below;
+            true
+        }
+        None => false,
+    }
+}
+
+/// The version of `item` in `layer`, without the items it overrides
+fn in_layer<T: Product + Clone>(item: &T, layer: Layer) -> Option<T> {
+    let mut item = item.clone();
+    while *item.layer() != layer {
+        item = *item.overridden().take()?;
+    }
+    *item.overridden() = None;
+    Some(item)
+}
+
+fn merge_items<T: Product>(
+    mine: &mut Vec<T>,
+    theirs: Vec<T>,
+    strategy: MergeStrategy,
+    layer: Layer,
+    report: &mut MergeReport,
+) {
+    let mut index = index_of(mine);
+    for mut item in theirs {
+        *item.layer() = layer;
+        match (index.get(&item.key()), strategy) {
+            (None, _) => {
+                index.insert(item.key(), mine.len());
+                mine.push(item);
+                report.added += 1;
+            }
+            (Some(_), MergeStrategy::KeepMine) => report.kept += 1,
+            (Some(&i), MergeStrategy::TakeTheirs) => {
+                stack(&mut mine[i], item);
+                report.replaced += 1;
+            }
+            (Some(_), MergeStrategy::KeepBoth) => {
+                let model = item.model().clone();
+                for n in 2.. {
+                    *item.model() = format!("{model} ({n})");
+                    if !index.contains_key(&item.key()) {
+                        break;
+                    }
+                }
+                index.insert(item.key(), mine.len());
+                mine.push(item);
+                report.added += 1;
+            }
+        }
+    }
+}
+
+/// Puts the company and personal items of `mine` over `built_in`, see [`Library::rebase`].
+/// Returns the new index of each item of `mine`, `None` if it was dropped.
+fn rebase_items<T: Product + Clone + PartialEq>(
+    mine: &mut Vec<T>,
+    built_in: Vec<T>,
+    used: impl Fn(usize) -> bool,
+) -> Vec<Option<usize>> {
+    let mut index = index_of(&built_in);
+    let old = std::mem::replace(mine, built_in.clone());
+    let mut moved = vec![];
+    for (i, item) in old.into_iter().enumerate() {
+        let key = item.key();
+        let target = index.get(&key).copied();
+        // the stack of the item from the bottom, without copies of the built-in item
+        let mut layers = vec![];
+        let mut next = Some(item);
+        while let Some(mut item) = next {
+            next = item.overridden().take().map(|below| *below);
+            if *item.layer() == Layer::BuiltIn {
+                if target.is_some() || !used(i) {
+                    continue;
+                }
+                // gone from the built-in library, but still in a project
+                *item.layer() = Layer::Personal;
+            } else if let Some(j) = target {
+                let mut plain = item.clone();
+                *plain.layer() = Layer::BuiltIn;
+                if plain == built_in[j] {
+                    continue;
+                }
+            }
+            layers.push(item);
+        }
+        let mut layers = layers.into_iter().rev();
+        moved.push(match target {
+            Some(j) => {
+                layers.for_each(|item| stack(&mut mine[j], item));
+                Some(j)
+            }
+            None => layers.next().map(|mut top| {
+                layers.for_each(|item| stack(&mut top, item));
+                index.insert(key, mine.len());
+                mine.push(top);
+                mine.len() - 1
+            }),
+        });
+    }
+    moved
+}
+
+/// Points `ids` to the new indices in `moved`, dropping the items that are gone
+fn remap(ids: &mut Vec<usize>, moved: &[Option<usize>]) {
+    *ids = ids
+        .iter()
+        .filter_map(|id| moved.get(*id).copied().flatten())
+        .collect();
+}
+
+impl Library {
+    /// Tags all items as coming from `layer`
+    pub fn set_layer(&mut self, layer: Layer) {
+        self.panels.iter_mut().for_each(|p| p.layer = layer);
+        self.inverters.iter_mut().for_each(|i| i.layer = layer);
+        self.batteries.iter_mut().for_each(|b| b.layer = layer);
+        self.cost_items.iter_mut().for_each(|c| c.layer = layer);
+    }
+
+    /// Merges the items of `theirs` into this library as part of `layer`
+    pub fn merge(&mut self, theirs: Library, strategy: MergeStrategy, layer: Layer) -> MergeReport {
+        let mut report = MergeReport::default();
+        merge_items(
+            &mut self.panels,
+            theirs.panels,
+            strategy,
+            layer,
+            &mut report,
+        );
+        merge_items(
+            &mut self.inverters,
+            theirs.inverters,
+            strategy,
+            layer,
+            &mut report,
+        );
+        merge_items(
+            &mut self.batteries,
+            theirs.batteries,
+            strategy,
+            layer,
+            &mut report,
+        );
+        merge_items(
+            &mut self.cost_items,
+            theirs.cost_items,
+            strategy,
+            layer,
+            &mut report,
+        );
+        self.exchange_rates.merge(theirs.exchange_rates);
+        report
+    }
+
+    /// Replaces the built-in layer with `built_in`, the library shipped with this version,
+    /// and stacks the company and personal items over it by brand and model. Built-in items
+    /// that were removed are dropped, unless a project uses them: those are kept as personal
+    /// items. The projects are pointed to the new indices.
+    pub fn rebase(&mut self, built_in: Library, projects: &mut [Project]) {
+        let panels = rebase_items(&mut self.panels, built_in.panels, |i| {
+            projects.iter().any(|p| p.panels.contains(&i))
+        });
+        let inverters = rebase_items(&mut self.inverters, built_in.inverters, |i| {
+            projects.iter().any(|p| p.inverters.contains(&i))
+        });
+        let batteries = rebase_items(&mut self.batteries, built_in.batteries, |i| {
+            projects.iter().any(|p| p.batteries.contains(&i))
+        });
+        let cost_items = rebase_items(&mut self.cost_items, built_in.cost_items, |i| {
+            projects
+                .iter()
+                .any(|p| p.cost_items.iter().any(|line| line.item == i))
+        });
+        self.exchange_rates.merge(built_in.exchange_rates);
+        for project in projects {
+            remap(&mut project.panels, &panels);
+            remap(&mut project.inverters, &inverters);
+            remap(&mut project.batteries, &batteries);
+            project.cost_items = project
+                .cost_items
+                .iter()
+                .filter_map(|line| {
+                    let item = cost_items.get(line.item).copied().flatten()?;
+                    Some(CostLine { item, ..*line })
+                })
+                .collect();
+        }
+    }
+
+    /// The items of one layer, e.g. to export a company catalogue. Items overridden by a
+    /// higher layer are included as they are in `layer`.
+    pub fn layer(&self, layer: Layer) -> Library {
+        fn items<T: Product + Clone>(items: &[T], layer: Layer) -> Vec<T> {
+            items.iter().filter_map(|i| in_layer(i, layer)).collect()
+        }
+        Library {
+            panels: items(&self.panels, layer),
+            inverters: items(&self.inverters, layer),
+            batteries: items(&self.batteries, layer),
+            cost_items: items(&self.cost_items, layer),
+            exchange_rates: self.exchange_rates.clone(),
+        }
+    }
+}
+
+#[test]
+fn merging() {
+    let company = Library {
+        panels: vec![
+            Panel {
+                brand: "aleo ".into(),
+                model: "LEO Black 400 Wp Premium - L84S400".into(),
+                price: Money::from_amount(180.),
+                ..Default::default()
+            },
+            Panel {
+                brand: "Meyer Burger".into(),
+                model: "Black 400".into(),
+                ..Default::default()
+            },
+        ],
+        inverters: vec![],
+        batteries: vec![],
+        cost_items: vec![],
+        exchange_rates: crate::currency::ExchangeRates(vec![crate::currency::ExchangeRate {
+            currency: Currency("CHF".into()),
+            per_eur: 0.95,
+            date: crate::quotes::Date::new(2024, 1, 1),
+        }]),
+    };
+
+    let mut library = Library::default();
+    let report = library.merge(company.clone(), MergeStrategy::KeepMine, Layer::Company);
+    assert_eq!((report.added, report.replaced, report.kept), (1, 0, 1));
+    assert_eq!(library.panels[0].price.amount(), 206.);
+    assert_eq!(library.panels[0].layer, Layer::BuiltIn);
+
+    let mut library = Library::default();
+    library.merge(company.clone(), MergeStrategy::KeepBoth, Layer::Company);
+    library.merge(company.clone(), MergeStrategy::KeepBoth, Layer::Company);
+    assert_eq!(library.panels.len(), 5);
+    assert_eq!(
+        library.panels[3].model,
+        "LEO Black 400 Wp Premium - L84S400 (3)"
+    );
+
+    let personal = Library {
+        panels: vec![Panel {
+            brand: "Meyer Burger".into(),
+            model: "Black 400".into(),
+            price: Money::from_amount(250.),
+            ..Default::default()
+        }],
+        inverters: vec![],
+        batteries: vec![],
+        cost_items: vec![],
+        exchange_rates: Default::default(),
+    };
+    let mut library = Library::default();
+    library.merge(company.clone(), MergeStrategy::TakeTheirs, Layer::Company);
+    library.merge(personal, MergeStrategy::TakeTheirs, Layer::Personal);
+    assert_eq!(library.panels.len(), 2);
+    assert_eq!(library.panels[0].price.amount(), 180.);
+    assert_eq!(library.panels[0].layer, Layer::Company);
+    assert_eq!(library.panels[1].price.amount(), 250.);
+    assert_eq!(library.panels[1].layer, Layer::Personal);
+    // the overridden items are kept
+    let built_in = library.layer(Layer::BuiltIn);
+    assert_eq!(built_in.panels.len(), 1);
+    assert_eq!(built_in.panels[0].price.amount(), 206.);
+    assert_eq!(built_in.inverters.len(), 2);
+    assert_eq!(library.layer(Layer::Company).panels.len(), 2);
+    assert_eq!(
+        library.exchange_rates.per_eur(&Currency("CHF".into())),
+        Some(0.95)
+    );
+
+    // a new company catalogue goes under the personal override
+    let mut update = company;
+    update.panels[1].price = Money::from_amount(240.);
+    library.merge(update, MergeStrategy::TakeTheirs, Layer::Company);
+    assert_eq!(library.panels[1].price.amount(), 250.);
+    assert!(revert(&mut library.panels[1]));
+    assert_eq!(library.panels[1].price.amount(), 240.);
+    assert_eq!(library.panels[1].layer, Layer::Company);
+    assert!(revert(&mut library.panels[0]));
+    assert_eq!(library.panels[0].price.amount(), 206.);
+    assert!(!revert(&mut library.panels[0]));
+}
+
+#[test]
+fn rebasing() {
+    let mut built_in = Library::default();
+    let mut library = built_in.clone();
+    let personal = Panel {
+        brand: "Meyer Burger".into(),
+        model: "Black 400".into(),
+        ..Default::default()
+    };
+    library.panels.push(personal.clone());
+    library.batteries[0].price = Money::from_amount(1.);
+    library.batteries[0].layer = Layer::Personal;
+    // a copy from before layers, which changes nothing
+    library.inverters[1].layer = Layer::Personal;
+    let mut projects = vec![Project {
+        panels: vec![1],
+        inverters: vec![0, 1],
+        batteries: vec![1],
+        ..Default::default()
+    }];
+
+    // a new version drops inverter 0 and the panel, and changes a battery price
+    let gone = built_in.inverters.remove(0);
+    built_in.panels.clear();
+    built_in.batteries[0].price = Money::from_amount(2.);
+    built_in.batteries[1].price = Money::from_amount(3.);
+    library.rebase(built_in.clone(), &mut projects);
+
+    assert_eq!(library.inverters[0].layer, Layer::BuiltIn);
+    assert_eq!(library.inverters[1].model, gone.model);
+    assert_eq!(library.inverters[1].layer, Layer::Personal);
+    assert_eq!(projects[0].inverters, vec![1, 0]);
+    // the unused built-in panel is gone, the personal one is kept
+    assert_eq!(library.panels.len(), 1);
+    assert_eq!(library.panels[0].model, personal.model);
+    assert_eq!(projects[0].panels, vec![0]);
+    // the override stays over the new built-in price
+    assert_eq!(library.batteries[0].price.amount(), 1.);
+    assert!(revert(&mut library.batteries[0]));
+    assert_eq!(library.batteries[0].price.amount(), 2.);
+    assert_eq!(library.batteries[1].price.amount(), 3.);
+    assert_eq!(projects[0].batteries, vec![1]);
+
+    // nothing changes the second time
+    let before = library.clone();
+    library.rebase(built_in, &mut projects);
+    assert_eq!(library.panels, before.panels);
+    assert_eq!(library.inverters, before.inverters);
+    assert_eq!(projects[0].inverters, vec![1, 0]);
+}
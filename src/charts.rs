@@ -0,0 +1,292 @@
+use egui::Color32;
+use egui_plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints, Points, VLine};
+
+use crate::{
+    currency::Currency,
+    format,
+    optimizer::Optimization,
+    simulation::{month_name, Day, Hour, Month},
+    sizing::{Sizing, SizingPoint},
+    tr,
+};
+
+pub(crate) const PRODUCTION: Color32 = Color32::from_rgb(240, 180, 40);
+pub(crate) const CONSUMPTION: Color32 = Color32::from_rgb(90, 140, 220);
+pub(crate) const SELF_CONSUMPTION: Color32 = Color32::from_rgb(90, 190, 110);
+pub(crate) const FEED_IN: Color32 = Color32::from_rgb(200, 120, 60);
+pub(crate) const GRID_IMPORT: Color32 = Color32::from_rgb(160, 160, 160);
+
+fn month_axis(value: f64, _digits: usize, _range: &std::ops::RangeInclusive<f64>) -> String {
+    let m = value.round();
+    if (m - value).abs() < 0.01 && (0. ..12.).contains(&m) {
+        month_name(m as usize).chars().take(3).collect()
+    } else {
+        String::new()
+    }
+}
+
+/// Name, color and value of a chart series
+type Series = (String, Color32, fn(&Month) -> f32);
+
+/// Grouped bars of all energy flows per month
+pub fn monthly_chart(ui: &mut egui::Ui, months: &[Month]) {
+    let series: [Series; 5] = [
+        (tr!("production"), PRODUCTION, |m| m.production_kwh),
+        (tr!("consumption"), CONSUMPTION, |m| m.consumption_kwh),
+        (tr!("self_consumption"), SELF_CONSUMPTION, |m| {
+            m.self_consumption_kwh
+        }),
+        (tr!("feed_in"), FEED_IN, |m| m.feed_in_kwh),
+        (tr!("grid_import"), GRID_IMPORT, |m| m.grid_import_kwh),
+    ];
+    let width = 0.8 / series.len() as f64;
+
+    Plot::new("monthly")
+        .height(220.)
+        .legend(Legend::default())
+        .allow_scroll(false)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .x_axis_formatter(month_axis)
+        .y_axis_label("kWh")
+        .show(ui, |plot_ui| {
+            for (i, (name, color, value)) in series.into_iter().enumerate() {
+                let offset = (i as f64 - 2.) * width;
+                let bars = months
+                    .iter()
+                    .enumerate()
+                    .map(|(m, month)| {
+                        Bar::new(m as f64 + offset, value(month) as f64)
+                            .width(width)
+                            .name(month_name(m))
+                    })
+                    .collect();
+                plot_ui.bar_chart(BarChart::new(bars).color(color).name(name));
+            }
+        });
+}
+
+/// Production and consumption over an average day of the given month
+pub fn daily_profile_chart(ui: &mut egui::Ui, month: &Month) {
+    let line = |values: &[f32]| {
+        PlotPoints::from_iter(
+            values
+                .iter()
+                .enumerate()
+                .map(|(hour, v)| [hour as f64 + 0.5, *v as f64]),
+        )
+    };
+
+    Plot::new("daily_profile")
+        .height(200.)
+        .legend(Legend::default())
+        .allow_scroll(false)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .include_x(0.)
+        .include_x(24.)
+        .include_y(0.)
+        .x_axis_label(tr!("time_of_day"))
+        .y_axis_label("kWh")
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(line(&month.daily_production_kwh))
+                    .color(PRODUCTION)
+                    .fill(0.)
+                    .name(tr!("production")),
+            );
+            plot_ui.line(
+                Line::new(line(&month.daily_consumption_kwh))
+                    .color(CONSUMPTION)
+                    .name(tr!("consumption")),
+            );
+        });
+}
+
+/// Hover text like "PV 14:30: 2.41 kW"
+fn hour_label(unit: &'static str) -> impl Fn(&str, &egui_plot::PlotPoint) -> String {
+    move |name, point| {
+        let minutes = (point.x * 60.).round().clamp(0., 24. * 60.) as u32;
+        format!(
+            "{name}\n{:02}:{:02}  {} {unit}",
+            minutes / 60,
+            minutes % 60,
+            format::number(point.y as f32, 2)
+        )
+    }
+}
+
+/// PV power against the load and the battery state of charge over one day
+pub fn day_chart(ui: &mut egui::Ui, day: &Day) {
+    let line = |value: fn(&Hour) -> f32| {
+        PlotPoints::from_iter(
+            day.hours
+                .iter()
+                .enumerate()
+                .map(|(hour, h)| [hour as f64 + 0.5, value(h) as f64]),
+        )
+    };
+
+    Plot::new("day_power")
+        .height(200.)
+        .legend(Legend::default())
+        .link_axis("day", true, false)
+        .link_cursor("day", true, false)
+        .include_x(0.)
+        .include_x(24.)
+        .include_y(0.)
+        .y_axis_label("kW")
+        .label_formatter(hour_label("kW"))
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(line(|h| h.production_kwh))
+                    .color(PRODUCTION)
+                    .fill(0.)
+                    .name(tr!("pv_power")),
+            );
+            plot_ui.line(
+                Line::new(line(|h| h.consumption_kwh))
+                    .color(CONSUMPTION)
+                    .name(tr!("consumption")),
+            );
+            plot_ui.line(
+                Line::new(line(|h| h.grid_import_kwh))
+                    .color(GRID_IMPORT)
+                    .name(tr!("grid_import")),
+            );
+        });
+
+    Plot::new("day_soc")
+        .height(120.)
+        .legend(Legend::default())
+        .link_axis("day", true, false)
+        .link_cursor("day", true, false)
+        .include_x(0.)
+        .include_x(24.)
+        .include_y(0.)
+        .x_axis_label(tr!("time_of_day"))
+        .y_axis_label("kWh")
+        .label_formatter(hour_label("kWh"))
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(line(|h| h.soc_kwh))
+                    .color(SELF_CONSUMPTION)
+                    .fill(0.)
+                    .name(tr!("battery_soc")),
+            );
+        });
+}
+
+/// Autarky, self-consumption and net present value against the battery capacity
+pub fn sizing_chart(ui: &mut egui::Ui, sizing: &Sizing, currency: &Currency) {
+    let line = |value: fn(&SizingPoint) -> f32| {
+        PlotPoints::from_iter(
+            sizing
+                .points
+                .iter()
+                .map(|p| [p.capacity_kwh as f64, value(p) as f64]),
+        )
+    };
+    let markers = |plot_ui: &mut egui_plot::PlotUi| {
+        if let Some(knee) = sizing.knee() {
+            plot_ui.vline(
+                VLine::new(knee.capacity_kwh)
+                    .color(SELF_CONSUMPTION)
+                    .name(tr!("knee_point")),
+            );
+        }
+        if let Some(optimal) = sizing.optimal() {
+            plot_ui.vline(
+                VLine::new(optimal.capacity_kwh)
+                    .color(PRODUCTION)
+                    .name(tr!("economic_optimum")),
+            );
+        }
+    };
+
+    Plot::new("sizing_shares")
+        .height(180.)
+        .legend(Legend::default())
+        .link_axis("sizing", true, false)
+        .link_cursor("sizing", true, false)
+        .include_y(0.)
+        .include_y(100.)
+        .y_axis_label("%")
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(line(|p| p.autarky * 100.))
+                    .color(CONSUMPTION)
+                    .name(tr!("autarky")),
+            );
+            plot_ui.points(Points::new(line(|p| p.autarky * 100.)).color(CONSUMPTION));
+            plot_ui.line(
+                Line::new(line(|p| p.self_consumption * 100.))
+                    .color(FEED_IN)
+                    .name(tr!("self_consumption")),
+            );
+            markers(plot_ui);
+        });
+
+    Plot::new("sizing_npv")
+        .height(140.)
+        .legend(Legend::default())
+        .link_axis("sizing", true, false)
+        .link_cursor("sizing", true, false)
+        .x_axis_label(tr!("battery_capacity_kwh"))
+        .y_axis_label(currency.symbol())
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(line(|p| p.npv))
+                    .color(GRID_IMPORT)
+                    .name(tr!("npv")),
+            );
+            plot_ui.points(Points::new(line(|p| p.npv)).color(GRID_IMPORT));
+            markers(plot_ui);
+        });
+}
+
+/// Price against self-sufficiency of all configurations, with the Pareto front and the best one
+pub fn pareto_chart(ui: &mut egui::Ui, optimization: &Optimization, currency: &Currency) {
+    let point = |i: &usize| {
+        let c = &optimization.candidates[*i];
+        [c.price as f64, c.autarky as f64 * 100.]
+    };
+    let all = (0..optimization.candidates.len()).map(|i| point(&i));
+    let front = optimization.pareto.iter().map(point);
+    let currency = currency.clone();
+
+    Plot::new("pareto")
+        .height(220.)
+        .legend(Legend::default())
+        .include_y(0.)
+        .x_axis_label(tr!("total_cost"))
+        .y_axis_label(tr!("autarky"))
+        .label_formatter(move |name, point| {
+            format!(
+                "{name}\n{}, {}",
+                format::money(point.x as f32, &currency),
+                format::percent(point.y as f32 / 100.)
+            )
+        })
+        .show(ui, |plot_ui| {
+            plot_ui.points(
+                Points::new(PlotPoints::from_iter(all))
+                    .color(GRID_IMPORT)
+                    .radius(2.)
+                    .name(tr!("configurations")),
+            );
+            plot_ui.line(
+                Line::new(PlotPoints::from_iter(front))
+                    .color(CONSUMPTION)
+                    .name(tr!("pareto_front")),
+            );
+            if let Some(best) = optimization.best {
+                plot_ui.points(
+                    Points::new(PlotPoints::from_iter([point(&best)]))
+                        .color(PRODUCTION)
+                        .radius(5.)
+                        .name(tr!("best_configuration")),
+                );
+            }
+        });
+}
@@ -0,0 +1,648 @@
+//! Headless mode for batch calculations, e.g. `pvcalc --cli --format csv quotes/*.json`
+
+use std::{fs::File, io::Write, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::{analyze, Analysis},
+    catalogue::MergeStrategy,
+    components::{Layer, Library, Project},
+    currency::Currency,
+    import::import,
+    quotes::Date,
+    report::{file_name, ExportFormat, Report},
+    timeseries::TimeSeries,
+    translation::{check_catalogue, scan_sources, update_catalogue, Catalogue, LOCALES},
+};
+
+const USAGE: &str = "Usage: pvcalc --cli [OPTIONS] PROJECT.json...
+       pvcalc --cli i18n [I18N OPTIONS]
+       pvcalc --cli import [--library FILE] [--output FILE] DATA.csv...
+       pvcalc --cli merge [MERGE OPTIONS] MINE.json THEIRS.json...
+       pvcalc --cli report [REPORT OPTIONS] PROJECT.json...
+       pvcalc --cli timeseries [TIMESERIES OPTIONS] PROJECT.json...
+
+Calculates every project file and prints the results.
+A project file holds a single project or a list of scenarios.
+
+Options:
+  --library FILE    Library to resolve panels, batteries and inverters (default: built-in)
+  --format FORMAT   table, json or csv (default: table)
+  --output FILE     Write to FILE instead of stdout
+  --date YYYY-MM-DD Choose quotes for this day (default: the project's price date or today)
+  --help            Show this help
+
+The i18n command collects all tr! message IDs from the source code and
+reports new, stale and untranslated messages per locale.
+
+The import command adds panels, inverters, batteries and cost items from CSV
+files to a library: the CEC module and inverter databases of SAM/pvlib, or a
+file in the format of library_template.csv. Items with the same brand and model are
+updated. The result is written to --output, by default the --library file
+or lib.json.
+
+The merge command merges library files into the first one. Items with the
+same brand and model are conflicts, resolved by --strategy.
+
+The report command writes an offer for every scenario of the project files,
+named after the scenario.
+
+The timeseries command writes the hourly simulation of every scenario with
+monthly and yearly sums: NAME_hourly.csv, NAME_monthly.csv and NAME_yearly.csv,
+or one NAME.xlsx workbook with a sheet each.
+
+Merge options:
+  --strategy S      mine, theirs or both (default: theirs)
+  --layer LAYER     Tag merged items as company or personal (default: company)
+  --only LAYER      Write only the items of builtin, company or personal
+  --output FILE     Write to FILE instead of the first library
+
+Report options:
+  --library FILE    Library to resolve the components (default: built-in)
+  --format FORMAT   pdf, html or md (default: pdf)
+  --output-dir DIR  Directory for the reports (default: current directory)
+  --date YYYY-MM-DD Choose quotes for this day (default: the project's price date or today)
+
+Timeseries options:
+  --library FILE    Library to resolve the components (default: built-in)
+  --format FORMAT   csv or xlsx (default: csv, xlsx needs the xlsx feature)
+  --output-dir DIR  Directory for the files (default: current directory)
+
+I18n options:
+  --source DIR      Source code to scan (default: src)
+  --catalogue FILE  Catalogue to check (default: translations.json)
+  --write           Add new messages to the catalogue
+  --prune           Remove stale messages from the catalogue, implies --write
+  --check           Fail if a message is not translated for every locale";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Format {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    library: Option<PathBuf>,
+    format: Format,
+    output: Option<PathBuf>,
+    /// Overrides the price date of the projects
+    date: Option<Date>,
+    projects: Vec<PathBuf>,
+}
+
+/// Project files may hold a single project or several scenarios
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProjectFile {
+    Many(Vec<Project>),
+    One(Box<Project>),
+}
+
+#[derive(Serialize, Debug)]
+struct Row {
+    file: String,
+    name: String,
+    /// Of all money values
+    currency: Currency,
+    #[serde(flatten)]
+    analysis: Analysis,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--cli" => {}
+            "--help" | "-h" => return Ok(None),
+            "--library" => options.library = Some(value()?.into()),
+            "--output" | "-o" => options.output = Some(value()?.into()),
+            "--date" => options.date = Some(value()?.parse()?),
+            "--format" | "-f" => {
+                options.format = match value()?.as_str() {
+                    "table" => Format::Table,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("Unknown format {other}")),
+                }
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option {other}")),
+            _ => options.projects.push(arg.into()),
+        }
+    }
+    if options.projects.is_empty() {
+        return Err("No project file given".into());
+    }
+    Ok(Some(options))
+}
+
+fn load_library(path: &Option<PathBuf>) -> Result<Library, String> {
+    match path {
+        Some(path) => File::open(path)
+            .map_err(|e| format!("Can't open {}: {e}", path.display()))
+            .and_then(|f| {
+                serde_json::from_reader::<_, Library>(f)
+                    .map_err(|e| format!("Can't read {}: {e}", path.display()))
+            })
+            .map(|mut library| {
+                library.migrate();
+                library
+            }),
+        None => Ok(Library::default()),
+    }
+}
+
+/// Reads the scenarios of a project file, priced on `date` if given, and warns about prices
+/// left out for lack of an exchange rate
+fn load_projects(
+    path: &PathBuf,
+    library: &Library,
+    date: Option<Date>,
+) -> Result<Vec<Project>, String> {
+    let file = File::open(path).map_err(|e| format!("Can't open {}: {e}", path.display()))?;
+    let mut projects = match serde_json::from_reader(file)
+        .map_err(|e| format!("Can't read {}: {e}", path.display()))?
+    {
+        ProjectFile::Many(projects) => projects,
+        ProjectFile::One(project) => vec![*project],
+    };
+    for project in &mut projects {
+        project.migrate();
+        if date.is_some() {
+            project.price_date = date;
+        }
+        let missing = project.missing_rates(library);
+        if !missing.is_empty() {
+            let currencies = missing.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+            eprintln!(
+                "{}: {}: No exchange rate to convert {} into {}, left out of the price",
+                path.display(),
+                project.name,
+                currencies.join(", "),
+                project.currency
+            );
+        }
+    }
+    Ok(projects)
+}
+
+const COLUMNS: [&str; 12] = [
+    "file",
+    "name",
+    "kwp",
+    "currency",
+    "price_net",
+    "price_gross",
+    "price_sum",
+    "yield_year_kwh",
+    "self_consumption",
+    "combined_benefit",
+    "payback_years",
+    "npv",
+];
+
+fn cells(row: &Row) -> [String; 12] {
+    let f = &row.analysis;
+    [
+        row.file.clone(),
+        row.name.clone(),
+        format!("{:.2}", f.kwp),
+        row.currency.to_string(),
+        format!("{:.2}", f.price_net),
+        format!("{:.2}", f.price_gross),
+        format!("{:.2}", f.price_sum),
+        format!("{:.1}", f.yield_year_kwh),
+        format!("{:.3}", f.self_consumption),
+        format!("{:.2}", f.combined_benefit),
+        format!("{:.1}", f.payback_years),
+        format!("{:.2}", f.npv),
+    ]
+}
+
+fn render(rows: &[Row], format: Format) -> Result<String, String> {
+    match format {
+        Format::Json => serde_json::to_string_pretty(rows).map_err(|e| e.to_string()),
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            writer.write_record(COLUMNS).map_err(|e| e.to_string())?;
+            for row in rows {
+                writer.write_record(cells(row)).map_err(|e| e.to_string())?;
+            }
+            let data = writer.into_inner().map_err(|e| e.to_string())?;
+            String::from_utf8(data).map_err(|e| e.to_string())
+        }
+        Format::Table => {
+            let body = rows.iter().map(cells).collect::<Vec<_>>();
+            let widths = COLUMNS
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    body.iter()
+                        .map(|r| r[i].chars().count())
+                        .fold(c.len(), usize::max)
+                })
+                .collect::<Vec<_>>();
+            let line = |cells: Vec<String>| {
+                cells
+                    .iter()
+                    .zip(&widths)
+                    .map(|(c, w)| format!("{c:<w$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+                    + "\n"
+            };
+            let mut out = line(COLUMNS.iter().map(|c| c.to_string()).collect());
+            for cells in body {
+                out.push_str(&line(cells.to_vec()));
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Checks and updates the translation catalogue
+fn i18n(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut source = PathBuf::from("src");
+    let mut catalogue_path = PathBuf::from("translations.json");
+    let (mut write, mut prune, mut check) = (false, false, false);
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--source" => source = value()?.into(),
+            "--catalogue" => catalogue_path = value()?.into(),
+            "--write" => write = true,
+            "--prune" => (write, prune) = (true, true),
+            "--check" => check = true,
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            other => return Err(format!("Unknown option {other}")),
+        }
+    }
+
+    let used =
+        scan_sources(&source).map_err(|e| format!("Can't scan {}: {e}", source.display()))?;
+    let mut catalogue: Catalogue = File::open(&catalogue_path)
+        .map_err(|e| format!("Can't open {}: {e}", catalogue_path.display()))
+        .and_then(|f| {
+            serde_json::from_reader(f)
+                .map_err(|e| format!("Can't read {}: {e}", catalogue_path.display()))
+        })?;
+    let report = check_catalogue(&catalogue, &used);
+
+    println!("{} messages used in code", report.used);
+    if !report.new.is_empty() {
+        println!("New: {}", report.new.join(", "));
+    }
+    if !report.stale.is_empty() {
+        println!("Stale: {}", report.stale.join(", "));
+    }
+    for (locale, _) in LOCALES {
+        let missing = &report.missing[locale];
+        print!(
+            "{locale}: {:.1} % ({} missing)",
+            report.completeness(locale) * 100.,
+            missing.len()
+        );
+        if missing.is_empty() {
+            println!();
+        } else {
+            println!(": {}", missing.join(", "));
+        }
+    }
+
+    if write {
+        update_catalogue(&mut catalogue, &used, prune);
+        let json = serde_json::to_string_pretty(&catalogue).map_err(|e| e.to_string())? + "\n";
+        std::fs::write(&catalogue_path, json)
+            .map_err(|e| format!("Can't write {}: {e}", catalogue_path.display()))?;
+        println!("Updated {}", catalogue_path.display());
+    }
+    if check && report.missing.values().any(|m| !m.is_empty()) {
+        return Err("Translations are incomplete".into());
+    }
+    Ok(())
+}
+
+/// Imports CSV files into a library file
+fn import_files(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut library_path = None;
+    let mut output = None;
+    let mut files = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--library" => library_path = Some(PathBuf::from(value()?)),
+            "--output" | "-o" => output = Some(PathBuf::from(value()?)),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option {other}")),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        return Err("No CSV file given".into());
+    }
+
+    let mut library = load_library(&library_path)?;
+    for path in &files {
+        let file = File::open(path).map_err(|e| format!("Can't open {}: {e}", path.display()))?;
+        let report = import(&mut library, file)
+            .map_err(|e| format!("Can't import {}: {e}", path.display()))?;
+        println!(
+            "{}: {:?}, {} added, {} updated, {} skipped",
+            path.display(),
+            report.source,
+            report.added,
+            report.updated,
+            report.skipped
+        );
+    }
+
+    let output = output
+        .or(library_path)
+        .unwrap_or_else(|| PathBuf::from("lib.json"));
+    let json = serde_json::to_string_pretty(&library).map_err(|e| e.to_string())? + "\n";
+    std::fs::write(&output, json).map_err(|e| format!("Can't write {}: {e}", output.display()))?;
+    println!(
+        "{} panels, {} inverters, {} batteries written to {}",
+        library.panels.len(),
+        library.inverters.len(),
+        library.batteries.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn parse_layer(name: &str) -> Result<Layer, String> {
+    match name {
+        "builtin" => Ok(Layer::BuiltIn),
+        "company" => Ok(Layer::Company),
+        "personal" => Ok(Layer::Personal),
+        other => Err(format!("Unknown layer {other}")),
+    }
+}
+
+/// Merges library files into the first one
+fn merge_files(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut strategy = MergeStrategy::TakeTheirs;
+    let mut layer = Layer::Company;
+    let mut only = None;
+    let mut output = None;
+    let mut files = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--strategy" => {
+                strategy = match value()?.as_str() {
+                    "mine" => MergeStrategy::KeepMine,
+                    "theirs" => MergeStrategy::TakeTheirs,
+                    "both" => MergeStrategy::KeepBoth,
+                    other => return Err(format!("Unknown strategy {other}")),
+                }
+            }
+            "--layer" => layer = parse_layer(&value()?)?,
+            "--only" => only = Some(parse_layer(&value()?)?),
+            "--output" | "-o" => output = Some(PathBuf::from(value()?)),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option {other}")),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    let Some((mine, theirs)) = files.split_first() else {
+        return Err("No library file given".into());
+    };
+
+    let mut library = load_library(&Some(mine.clone()))?;
+    for path in theirs {
+        let report = library.merge(load_library(&Some(path.clone()))?, strategy, layer);
+        println!(
+            "{}: {} added, {} replaced, {} kept",
+            path.display(),
+            report.added,
+            report.replaced,
+            report.kept
+        );
+    }
+    if let Some(only) = only {
+        library = library.layer(only);
+    }
+
+    let output = output.unwrap_or_else(|| mine.clone());
+    let json = serde_json::to_string_pretty(&library).map_err(|e| e.to_string())? + "\n";
+    std::fs::write(&output, json).map_err(|e| format!("Can't write {}: {e}", output.display()))
+}
+
+/// Writes an offer for every scenario
+fn report_files(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut library_path = None;
+    let mut date = None;
+    let mut format = ExportFormat::Pdf;
+    let mut output_dir = PathBuf::from(".");
+    let mut files = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--library" => library_path = Some(PathBuf::from(value()?)),
+            "--output-dir" => output_dir = PathBuf::from(value()?),
+            "--date" => date = Some(value()?.parse()?),
+            "--format" | "-f" => {
+                format = match value()?.as_str() {
+                    "pdf" => ExportFormat::Pdf,
+                    "html" => ExportFormat::Html,
+                    "md" | "markdown" => ExportFormat::Markdown,
+                    other => return Err(format!("Unknown format {other}")),
+                }
+            }
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option {other}")),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        return Err("No project file given".into());
+    }
+
+    let library = load_library(&library_path)?;
+    for path in &files {
+        for project in load_projects(path, &library, date)? {
+            let report = Report::new(&project, &library, &analyze(&project, &library));
+            let output = output_dir.join(format!("{}.{}", file_name(&project), format.extension()));
+            std::fs::write(&output, format.render(&report))
+                .map_err(|e| format!("Can't write {}: {e}", output.display()))?;
+            println!("{}", output.display());
+        }
+    }
+    Ok(())
+}
+
+/// Writes the hourly results of every scenario
+fn timeseries_files(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut library_path = None;
+    let mut xlsx = false;
+    let mut output_dir = PathBuf::from(".");
+    let mut files = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--library" => library_path = Some(PathBuf::from(value()?)),
+            "--output-dir" => output_dir = PathBuf::from(value()?),
+            "--format" | "-f" => {
+                xlsx = match value()?.as_str() {
+                    "csv" => false,
+                    "xlsx" if cfg!(feature = "xlsx") => true,
+                    "xlsx" => return Err("Built without the xlsx feature".into()),
+                    other => return Err(format!("Unknown format {other}")),
+                }
+            }
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option {other}")),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        return Err("No project file given".into());
+    }
+
+    let library = load_library(&library_path)?;
+    for path in &files {
+        for project in load_projects(path, &library, None)? {
+            let series = TimeSeries::new(&project, &library);
+            let mut outputs = vec![];
+            #[cfg(feature = "xlsx")]
+            if xlsx {
+                outputs.push((format!("{}.xlsx", file_name(&project)), series.xlsx()?));
+            }
+            if !xlsx {
+                for sheet in [series.hourly(), series.monthly(), series.yearly()] {
+                    let name = format!("{}_{}.csv", file_name(&project), sheet.name);
+                    outputs.push((name, sheet.csv()?.into_bytes()));
+                }
+            }
+            for (name, data) in outputs {
+                let output = output_dir.join(name);
+                std::fs::write(&output, data)
+                    .map_err(|e| format!("Can't write {}: {e}", output.display()))?;
+                println!("{}", output.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the command line interface. `args` excludes the program name.
+pub fn run(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let mut args = args.into_iter().filter(|arg| arg != "--cli").peekable();
+    if args.peek().is_some_and(|arg| arg == "i18n") {
+        return i18n(args.skip(1));
+    }
+    if args.peek().is_some_and(|arg| arg == "import") {
+        return import_files(args.skip(1));
+    }
+    if args.peek().is_some_and(|arg| arg == "merge") {
+        return merge_files(args.skip(1));
+    }
+    if args.peek().is_some_and(|arg| arg == "report") {
+        return report_files(args.skip(1));
+    }
+    if args.peek().is_some_and(|arg| arg == "timeseries") {
+        return timeseries_files(args.skip(1));
+    }
+    let Some(options) = parse_args(args)? else {
+        println!("{USAGE}");
+        return Ok(());
+    };
+    let library = load_library(&options.library)?;
+
+    let mut rows = vec![];
+    for path in &options.projects {
+        for project in load_projects(path, &library, options.date)? {
+            rows.push(Row {
+                file: path.display().to_string(),
+                name: project.name.clone(),
+                currency: project.currency.clone(),
+                analysis: analyze(&project, &library),
+            });
+        }
+    }
+
+    let out = render(&rows, options.format)?;
+    match &options.output {
+        Some(path) => File::create(path)
+            .and_then(|mut f| f.write_all(out.as_bytes()))
+            .map_err(|e| format!("Can't write {}: {e}", path.display())),
+        None => {
+            print!("{out}");
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn batch() {
+    let options = parse_args(
+        [
+            "--cli",
+            "--format",
+            "csv",
+            "--date",
+            "2024-03-01",
+            "a.json",
+            "b.json",
+        ]
+        .iter()
+        .map(|s| s.to_string()),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(options.format, Format::Csv);
+    assert_eq!(options.projects.len(), 2);
+    assert_eq!(options.date, Some(Date::new(2024, 3, 1)));
+    assert!(parse_args(["--format".to_string()]).is_err());
+    assert!(parse_args(["--date".to_string(), "2024-3".to_string()]).is_err());
+
+    let library = Library::default();
+    let project = Project {
+        name: "Mit, Komma".into(),
+        panels: vec![0; 10],
+        price_date: options.date,
+        ..Default::default()
+    };
+    let rows = vec![Row {
+        file: "a.json".into(),
+        name: project.name.clone(),
+        currency: project.currency.clone(),
+        analysis: analyze(&project, &library),
+    }];
+    let csv = render(&rows, Format::Csv).unwrap();
+    assert!(csv.starts_with("file,name,kwp"));
+    assert!(csv.contains("a.json,\"Mit, Komma\",4.00,"));
+    let json: serde_json::Value =
+        serde_json::from_str(&render(&rows, Format::Json).unwrap()).unwrap();
+    assert_eq!(json[0]["kwp"], 4.0);
+    assert_eq!(render(&rows, Format::Table).unwrap().lines().count(), 2);
+}
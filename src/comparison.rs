@@ -0,0 +1,129 @@
+use egui::RichText;
+
+use crate::{
+    analysis::Analysis,
+    components::{Library, Project},
+    currency::Currency,
+    format, tr,
+};
+
+/// Which direction makes a value "better" when comparing scenarios
+enum Best {
+    Highest,
+    Lowest,
+}
+
+struct Row {
+    label: String,
+    best: Best,
+    value: fn(&Analysis) -> f32,
+    format: fn(f32, &Currency) -> String,
+}
+
+fn rows() -> Vec<Row> {
+    vec![
+        Row {
+            label: tr!("peak_power"),
+            best: Best::Highest,
+            value: |f| f.kwp,
+            format: |v, _| format::kwp(v),
+        },
+        Row {
+            label: tr!("total_cost"),
+            best: Best::Lowest,
+            value: |f| f.price_sum,
+            format: format::money,
+        },
+        Row {
+            label: tr!("yield_per_year"),
+            best: Best::Highest,
+            value: |f| f.yield_year_kwh,
+            format: |v, _| format::kwh(v),
+        },
+        Row {
+            label: tr!("self_consumption"),
+            best: Best::Highest,
+            value: |f| f.self_consumption,
+            format: |v, _| format::percent(v),
+        },
+        Row {
+            label: tr!("payback"),
+            best: Best::Lowest,
+            value: |f| f.payback_years,
+            format: |v, _| tr!("years_value", years = format::years(v)),
+        },
+        Row {
+            label: tr!("npv"),
+            best: Best::Highest,
+            value: |f| f.npv,
+            format: format::money,
+        },
+    ]
+}
+
+/// Index of the best finite value, if any
+fn best_index(values: &[f32], best: &Best) -> Option<usize> {
+    values
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| v.is_finite())
+        .min_by(|(_, a), (_, b)| match best {
+            Best::Highest => b.total_cmp(a),
+            Best::Lowest => a.total_cmp(b),
+        })
+        .map(|(i, _)| i)
+}
+
+/// Shows the key figures of all scenarios side by side in `currency`, highlighting the best
+/// one per row
+pub fn comparison_ui(
+    ui: &mut egui::Ui,
+    projects: &[Project],
+    analyses: &[&Analysis],
+    library: &Library,
+    currency: &Currency,
+) {
+    let analyses = projects
+        .iter()
+        .zip(analyses)
+        .map(|(p, a)| {
+            // unknown if there is no rate
+            let factor = library.exchange_rates.factor(&p.currency, currency);
+            (*a).clone().converted(factor.unwrap_or(f32::NAN))
+        })
+        .collect::<Vec<_>>();
+
+    egui::Grid::new("comparison")
+        .striped(true)
+        .num_columns(projects.len() + 1)
+        .show(ui, |ui| {
+            ui.label("");
+            for project in projects {
+                ui.strong(&project.name);
+            }
+            ui.end_row();
+
+            for row in rows() {
+                ui.label(&row.label);
+                let values = analyses.iter().map(row.value).collect::<Vec<_>>();
+                let best = best_index(&values, &row.best).filter(|_| values.len() > 1);
+                for (i, value) in values.iter().enumerate() {
+                    let text = RichText::new((row.format)(*value, currency));
+                    if Some(i) == best {
+                        ui.label(text.strong().color(ui.visuals().warn_fg_color));
+                    } else {
+                        ui.label(text);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+#[test]
+fn best_value() {
+    assert_eq!(best_index(&[1., 3., 2.], &Best::Highest), Some(1));
+    assert_eq!(best_index(&[1., 3., 2.], &Best::Lowest), Some(0));
+    assert_eq!(best_index(&[f32::INFINITY, 3.], &Best::Lowest), Some(1));
+    assert_eq!(best_index(&[], &Best::Lowest), None);
+}
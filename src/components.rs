@@ -1,35 +1,285 @@
 use egui::{Vec2, Widget};
 use serde::{Deserialize, Serialize};
 
-use crate::tr;
+use crate::{
+    catalogue::Product,
+    currency::{currency_edit, Currency, ExchangeRates},
+    format, panel_orientation,
+    quotes::{net, quotes_ui, Date, PriceSelection, Pricing, Quote},
+    simulation::Storage,
+    tax::{percent_edit, CostCategory, Tax},
+    tr,
+    units::{self, Area, Charge, Energy, EnergyPrice, Money, Power, Voltage},
+};
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+/// Which library an item comes from. Later layers override earlier ones.
+#[derive(
+    Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum Layer {
+    /// Shipped with pvcalc
+    BuiltIn,
+    /// Shared within a team
+    Company,
+    /// Added or changed by the user
+    #[default]
+    Personal,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::BuiltIn, Layer::Company, Layer::Personal];
+
+    pub fn label(&self) -> String {
+        match self {
+            Layer::BuiltIn => tr!("layer_builtin"),
+            Layer::Company => tr!("layer_company"),
+            Layer::Personal => tr!("layer_personal"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 #[serde(default)]
 pub struct Panel {
     pub brand: String,
     pub model: String,
     pub size_cm: Vec2,
-    pub price_eur: f32,
-    pub energy_wp: f32,
+    #[serde(alias = "price_eur", with = "units::money")]
+    pub price: Money,
+    pub price_gross: bool,
+    pub vat_rate: f32,
+    pub currency: Currency,
+    #[serde(rename = "energy_wp", with = "units::watts")]
+    pub peak_power: Power,
+    pub quotes: Vec<Quote>,
+    pub layer: Layer,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overridden: Option<Box<Panel>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 #[serde(default)]
-pub struct Battery {
+pub struct Inverter {
     pub brand: String,
     pub model: String,
-    pub price_eur: f32,
-    pub energy_ahr: f32,
-    pub voltage: f32,
+    #[serde(alias = "price_eur", with = "units::money")]
+    pub price: Money,
+    pub price_gross: bool,
+    pub vat_rate: f32,
+    pub currency: Currency,
+    /// Maximum PV power on the DC side
+    #[serde(rename = "kwp_max", with = "units::kilowatts")]
+    pub max_pv_power: Power,
+    /// Rated AC output
+    #[serde(rename = "energy_out", with = "units::kilowatts")]
+    pub ac_power: Power,
+    /// 1 or 3, 0 if unknown
+    pub phases: u8,
+    pub quotes: Vec<Quote>,
+    pub layer: Layer,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overridden: Option<Box<Inverter>>,
+}
+
+impl Panel {
+    pub fn area(&self) -> Area {
+        Area::from_cm2(self.size_cm.x * self.size_cm.y)
+    }
+
+    /// 0-1, share of the irradiance of 1000 W/m² at standard test conditions turned into power
+    pub fn efficiency(&self) -> f32 {
+        match self.area().m2() {
+            area if area > 0. => self.peak_power.w() / (area * 1000.),
+            _ => 0.,
+        }
+    }
+}
+
+/// Cell chemistry of a battery, which determines its typical usable share, efficiency and life
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum Chemistry {
+    /// Lithium iron phosphate
+    #[default]
+    Lfp,
+    /// Lithium nickel manganese cobalt
+    Nmc,
+    LeadAcid,
+}
+
+impl Chemistry {
+    pub const ALL: [Chemistry; 3] = [Chemistry::Lfp, Chemistry::Nmc, Chemistry::LeadAcid];
+
+    pub fn label(&self) -> String {
+        match self {
+            Chemistry::Lfp => "LFP".into(),
+            Chemistry::Nmc => "NMC".into(),
+            Chemistry::LeadAcid => tr!("lead_acid"),
+        }
+    }
+
+    /// Share of the nominal capacity that may be used
+    pub fn depth_of_discharge(&self) -> f32 {
+        match self {
+            Chemistry::Lfp => 0.95,
+            Chemistry::Nmc => 0.9,
+            Chemistry::LeadAcid => 0.5,
+        }
+    }
+
+    pub fn efficiency(&self) -> f32 {
+        match self {
+            Chemistry::Lfp => 0.95,
+            Chemistry::Nmc => 0.92,
+            Chemistry::LeadAcid => 0.8,
+        }
+    }
+
+    pub fn cycles(&self) -> u32 {
+        match self {
+            Chemistry::Lfp => 6000,
+            Chemistry::Nmc => 3000,
+            Chemistry::LeadAcid => 1200,
+        }
+    }
+
+    pub fn calendar_life_years(&self) -> f32 {
+        match self {
+            Chemistry::Lfp => 15.,
+            Chemistry::Nmc => 12.,
+            Chemistry::LeadAcid => 8.,
+        }
+    }
 }
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+
+/// A battery module. `series` x `parallel` modules form the bank that is installed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
-pub struct Inverter {
+pub struct Battery {
     pub brand: String,
     pub model: String,
-    pub price_eur: f32,
-    pub kwp_max: f32,
-    pub energy_out: f32,
+    /// Price of one module
+    #[serde(alias = "price_eur", with = "units::money")]
+    pub price: Money,
+    pub price_gross: bool,
+    pub vat_rate: f32,
+    pub currency: Currency,
+    pub chemistry: Chemistry,
+    #[serde(rename = "nominal_kwh", with = "units::kilowatt_hours")]
+    pub nominal: Energy,
+    #[serde(rename = "usable_kwh", with = "units::kilowatt_hours")]
+    pub usable: Energy,
+    /// Nominal voltage of one module
+    #[serde(with = "units::volts")]
+    pub voltage: Voltage,
+    #[serde(rename = "max_charge_kw", with = "units::kilowatts")]
+    pub max_charge: Power,
+    #[serde(rename = "max_discharge_kw", with = "units::kilowatts")]
+    pub max_discharge: Power,
+    /// 0-1, round trip
+    pub efficiency: f32,
+    /// Full cycles covered by the warranty
+    pub cycles: u32,
+    pub calendar_life_years: f32,
+    /// Modules in series, which multiplies the voltage
+    pub series: u32,
+    /// Strings in parallel, which multiplies the current
+    pub parallel: u32,
+    /// Capacity of blocks from before batteries were given in kWh, converted by [`Library::migrate`]
+    #[serde(rename = "energy_ahr", with = "units::amp_hours", skip_serializing)]
+    pub legacy_charge: Charge,
+    /// Quotes for one module
+    pub quotes: Vec<Quote>,
+    pub layer: Layer,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overridden: Option<Box<Battery>>,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        let chemistry = Chemistry::default();
+        Self {
+            brand: Default::default(),
+            model: Default::default(),
+            price: Default::default(),
+            price_gross: false,
+            vat_rate: 0.,
+            currency: Currency::default(),
+            chemistry,
+            nominal: Default::default(),
+            usable: Default::default(),
+            voltage: Default::default(),
+            max_charge: Default::default(),
+            max_discharge: Default::default(),
+            efficiency: chemistry.efficiency(),
+            cycles: chemistry.cycles(),
+            calendar_life_years: chemistry.calendar_life_years(),
+            series: 1,
+            parallel: 1,
+            legacy_charge: Default::default(),
+            quotes: Default::default(),
+            layer: Default::default(),
+            overridden: None,
+        }
+    }
+}
+
+impl Battery {
+    /// Number of modules in the bank
+    pub fn modules(&self) -> u32 {
+        self.series.max(1) * self.parallel.max(1)
+    }
+
+    pub fn bank_price(&self, pricing: &Pricing) -> Option<Money> {
+        Some(self.price(pricing)? * self.modules() as f32)
+    }
+
+    pub fn bank_nominal(&self) -> Energy {
+        self.nominal * self.modules() as f32
+    }
+
+    pub fn bank_usable(&self) -> Energy {
+        self.usable * self.modules() as f32
+    }
+
+    pub fn bank_voltage(&self) -> Voltage {
+        self.voltage * self.series.max(1) as f32
+    }
+
+    pub fn bank_max_charge(&self) -> Power {
+        self.max_charge * self.modules() as f32
+    }
+
+    pub fn bank_max_discharge(&self) -> Power {
+        self.max_discharge * self.modules() as f32
+    }
+
+    /// Years until the bank has to be replaced when cycled `cycles_per_year` full cycles
+    pub fn life_years(&self, cycles_per_year: f32) -> f32 {
+        if cycles_per_year > 0. {
+            self.calendar_life_years
+                .min(self.cycles as f32 / cycles_per_year)
+        } else {
+            self.calendar_life_years
+        }
+    }
+
+    /// Converts a lead-acid block given in Ah into the kWh based model
+    fn migrate(&mut self) {
+        if self.legacy_charge.ah() <= 0. || self.nominal.kwh() > 0. {
+            return;
+        }
+        let chemistry = Chemistry::LeadAcid;
+        self.nominal = self.legacy_charge * self.voltage;
+        self.usable = self.nominal * chemistry.depth_of_discharge();
+        // 0.5 C
+        self.max_charge = Power::from_kw(self.nominal.kwh() * 0.5);
+        self.max_discharge = self.max_charge;
+        self.chemistry = chemistry;
+        self.efficiency = chemistry.efficiency();
+        self.cycles = chemistry.cycles();
+        self.calendar_life_years = chemistry.calendar_life_years();
+        self.legacy_charge = Charge::default();
+    }
 }
 
 impl Widget for &mut Battery {
@@ -38,20 +288,83 @@ impl Widget for &mut Battery {
             .striped(true)
             .num_columns(2)
             .show(ui, |ui| {
-                ui.label(tr!("Marke"));
+                ui.label(tr!("brand"));
                 ui.text_edit_singleline(&mut self.brand);
                 ui.end_row();
-                ui.label(tr!("Modell"));
+                ui.label(tr!("model"));
                 ui.text_edit_singleline(&mut self.model);
                 ui.end_row();
-                ui.label(tr!("Preis"));
-                ui.add(egui::DragValue::new(&mut self.price_eur).suffix(" Eur"));
+                ui.label(tr!("price"));
+                ui.horizontal(|ui| {
+                    ui.add(self.price.drag_in(&self.currency));
+                    currency_edit(ui, &mut self.currency);
+                    vat_edit(ui, &mut self.price_gross, &mut self.vat_rate);
+                });
+                ui.end_row();
+                ui.label(tr!("chemistry"));
+                ui.horizontal(|ui| {
+                    for chemistry in Chemistry::ALL {
+                        ui.selectable_value(&mut self.chemistry, chemistry, chemistry.label());
+                    }
+                });
+                ui.end_row();
+                ui.label(tr!("nominal_capacity"));
+                ui.add(self.nominal.drag_kwh().speed(0.1));
+                ui.end_row();
+                ui.label(tr!("usable_capacity"));
+                ui.add(self.usable.drag_kwh().speed(0.1));
+                ui.end_row();
+                ui.label(tr!("voltage"));
+                ui.add(self.voltage.drag_v());
+                ui.end_row();
+                ui.label(tr!("max_charge_discharge"));
+                ui.horizontal(|ui| {
+                    ui.add(self.max_charge.drag_kw().speed(0.1));
+                    ui.add(self.max_discharge.drag_kw().speed(0.1));
+                });
+                ui.end_row();
+                ui.label(tr!("round_trip_efficiency"));
+                ui.add(
+                    egui::DragValue::new(&mut self.efficiency)
+                        .clamp_range(0.5..=1.)
+                        .speed(0.01),
+                );
+                ui.end_row();
+                ui.label(tr!("warranted_cycles"));
+                ui.add(egui::DragValue::new(&mut self.cycles).speed(10));
+                ui.end_row();
+                ui.label(tr!("calendar_life"));
+                ui.add(
+                    egui::DragValue::new(&mut self.calendar_life_years)
+                        .clamp_range(1.0..=30.)
+                        .suffix(tr!("years_suffix")),
+                );
                 ui.end_row();
-                ui.label(tr!("Leistung"));
-                ui.add(egui::DragValue::new(&mut self.energy_ahr).suffix(" ahr"));
+                ui.label(tr!("quotes"));
+                ui.vertical(|ui| quotes_ui(ui, &mut self.quotes));
+                ui.end_row();
+                ui.label(tr!("series_parallel"));
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.series).clamp_range(1..=16));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(&mut self.parallel).clamp_range(1..=16));
+                });
                 ui.end_row();
-                ui.label(tr!("Spannung"));
-                ui.add(egui::DragValue::new(&mut self.voltage).suffix(" V"));
+                ui.label(tr!("bank"));
+                ui.label(tr!(
+                    "bank_value",
+                    usable = format::number(self.bank_usable().kwh(), 1),
+                    voltage = format::number(self.bank_voltage().v(), 0),
+                    price = format::money(
+                        self.bank_price(&Pricing {
+                            currency: self.currency.clone(),
+                            ..Default::default()
+                        })
+                        .unwrap_or_default()
+                        .amount(),
+                        &self.currency
+                    )
+                ));
                 ui.end_row();
             })
             .response
@@ -64,20 +377,34 @@ impl Widget for &mut Inverter {
             .striped(true)
             .num_columns(2)
             .show(ui, |ui| {
-                ui.label(tr!("Marke"));
+                ui.label(tr!("brand"));
                 ui.text_edit_singleline(&mut self.brand);
                 ui.end_row();
-                ui.label(tr!("Modell"));
+                ui.label(tr!("model"));
                 ui.text_edit_singleline(&mut self.model);
                 ui.end_row();
-                ui.label(tr!("Preis"));
-                ui.add(egui::DragValue::new(&mut self.price_eur).suffix(" Eur"));
+                ui.label(tr!("price"));
+                ui.horizontal(|ui| {
+                    ui.add(self.price.drag_in(&self.currency));
+                    currency_edit(ui, &mut self.currency);
+                    vat_edit(ui, &mut self.price_gross, &mut self.vat_rate);
+                });
+                ui.end_row();
+                ui.label(tr!("kwp_max"));
+                ui.add(self.max_pv_power.drag_kw().suffix(" kWp"));
                 ui.end_row();
-                ui.label(tr!("kWp max"));
-                ui.add(egui::DragValue::new(&mut self.kwp_max).suffix(" kWp"));
+                ui.label(tr!("power"));
+                ui.add(self.ac_power.drag_kw());
+                ui.end_row();
+                ui.label(tr!("phases"));
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.phases, 0, "?");
+                    ui.selectable_value(&mut self.phases, 1, "1");
+                    ui.selectable_value(&mut self.phases, 3, "3");
+                });
                 ui.end_row();
-                ui.label(tr!("Leistung"));
-                ui.add(egui::DragValue::new(&mut self.energy_out).suffix(" kW"));
+                ui.label(tr!("quotes"));
+                ui.vertical(|ui| quotes_ui(ui, &mut self.quotes));
                 ui.end_row();
             })
             .response
@@ -90,30 +417,156 @@ impl Widget for &mut Panel {
             .striped(true)
             .num_columns(2)
             .show(ui, |ui| {
-                ui.label(tr!("Marke"));
+                ui.label(tr!("brand"));
                 ui.text_edit_singleline(&mut self.brand);
                 ui.end_row();
-                ui.label(tr!("Modell"));
+                ui.label(tr!("model"));
                 ui.text_edit_singleline(&mut self.model);
                 ui.end_row();
-                ui.label(tr!("Groesse (W x H)"));
+                ui.label(tr!("size"));
                 ui.horizontal(|ui| {
                     ui.add(egui::DragValue::new(&mut self.size_cm.x).suffix(" cm"));
                     ui.add(egui::DragValue::new(&mut self.size_cm.y).suffix(" cm"));
                 });
                 ui.end_row();
-                ui.label("Price");
-                ui.add(egui::DragValue::new(&mut self.price_eur).suffix(" Eur"));
+                ui.label(tr!("price"));
+                ui.horizontal(|ui| {
+                    ui.add(self.price.drag_in(&self.currency));
+                    currency_edit(ui, &mut self.currency);
+                    vat_edit(ui, &mut self.price_gross, &mut self.vat_rate);
+                });
+                ui.end_row();
+                ui.label(tr!("power"));
+                ui.add(self.peak_power.drag_w().suffix(" Wp"));
                 ui.end_row();
-                ui.label("Energy output");
-                ui.add(egui::DragValue::new(&mut self.energy_wp).suffix(" wp"));
+                ui.label(tr!("quotes"));
+                ui.vertical(|ui| quotes_ui(ui, &mut self.quotes));
                 ui.end_row();
             })
             .response
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Whether a list price includes VAT, and at which rate
+fn vat_edit(ui: &mut egui::Ui, gross: &mut bool, vat_rate: &mut f32) {
+    ui.checkbox(gross, tr!("gross"));
+    if *gross {
+        percent_edit(ui, vat_rate).on_hover_text(tr!("included_vat_hint"));
+    }
+}
+
+/// What the price of a cost item refers to
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum CostUnit {
+    #[default]
+    Piece,
+    /// Per panel of the project, e.g. mounting rails
+    Module,
+    /// Per kWp of the project, e.g. a dealer's surcharge
+    Kwp,
+    Meter,
+    Hour,
+    /// Once per project, e.g. scaffolding
+    Flat,
+}
+
+impl CostUnit {
+    pub const ALL: [CostUnit; 6] = [
+        CostUnit::Piece,
+        CostUnit::Module,
+        CostUnit::Kwp,
+        CostUnit::Meter,
+        CostUnit::Hour,
+        CostUnit::Flat,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            CostUnit::Piece => tr!("per_piece"),
+            CostUnit::Module => tr!("per_module"),
+            CostUnit::Kwp => tr!("per_kwp"),
+            CostUnit::Meter => tr!("per_meter"),
+            CostUnit::Hour => tr!("per_hour"),
+            CostUnit::Flat => tr!("flat_rate"),
+        }
+    }
+
+    /// Unit of the quantity in a bill of materials
+    pub fn symbol(&self) -> String {
+        match self {
+            CostUnit::Piece | CostUnit::Module => tr!("pieces_short"),
+            CostUnit::Kwp => "kWp".into(),
+            CostUnit::Meter => "m".into(),
+            CostUnit::Hour => "h".into(),
+            CostUnit::Flat => tr!("flat_short"),
+        }
+    }
+
+    /// What the quantity of a cost line is multiplied with
+    fn base(&self, panels: usize, kwp: f32) -> f32 {
+        match self {
+            CostUnit::Module => panels as f32,
+            CostUnit::Kwp => kwp,
+            _ => 1.,
+        }
+    }
+}
+
+/// Anything else on the bill: mounting material, cables, protection, labour, ...
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct CostItem {
+    pub brand: String,
+    /// What it is, e.g. "DC cable 6 mm²"
+    pub model: String,
+    /// Price per unit
+    #[serde(alias = "price_eur", with = "units::money")]
+    pub price: Money,
+    pub price_gross: bool,
+    pub vat_rate: f32,
+    pub currency: Currency,
+    pub unit: CostUnit,
+    pub quotes: Vec<Quote>,
+    pub layer: Layer,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overridden: Option<Box<CostItem>>,
+}
+
+impl Widget for &mut CostItem {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::Grid::new("cost_item")
+            .striped(true)
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr!("brand"));
+                ui.text_edit_singleline(&mut self.brand);
+                ui.end_row();
+                ui.label(tr!("description"));
+                ui.text_edit_singleline(&mut self.model);
+                ui.end_row();
+                ui.label(tr!("price"));
+                ui.horizontal(|ui| {
+                    ui.add(self.price.drag_in(&self.currency).speed(0.1));
+                    currency_edit(ui, &mut self.currency);
+                    vat_edit(ui, &mut self.price_gross, &mut self.vat_rate);
+                    egui::ComboBox::from_id_source("cost_unit")
+                        .selected_text(self.unit.label())
+                        .show_ui(ui, |ui| {
+                            for unit in CostUnit::ALL {
+                                ui.selectable_value(&mut self.unit, unit, unit.label());
+                            }
+                        });
+                });
+                ui.end_row();
+                ui.label(tr!("quotes"));
+                ui.vertical(|ui| quotes_ui(ui, &mut self.quotes));
+                ui.end_row();
+            })
+            .response
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 /// The library holds all things you can use in your project.
 pub struct Library {
     pub panels: Vec<Panel>,
@@ -121,17 +574,36 @@ pub struct Library {
     pub batteries: Vec<Battery>,
     #[serde(default)]
     pub inverters: Vec<Inverter>,
+    #[serde(default)]
+    pub cost_items: Vec<CostItem>,
+    /// To convert prices into the currency of a project
+    #[serde(default)]
+    pub exchange_rates: ExchangeRates,
 }
 
 impl Default for Library {
     fn default() -> Self {
-        serde_json::from_str(include_str!("../lib.json")).expect("Library must load")
+        let mut library: Library =
+            serde_json::from_str(include_str!("../lib.json")).expect("Library must load");
+        library.set_layer(Layer::BuiltIn);
+        library
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Library {
+    /// Upgrades entries saved by older versions
+    pub fn migrate(&mut self) {
+        for battery in &mut self.batteries {
+            battery.migrate();
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct Project {
+    /// Name of this scenario, e.g. "Mit Batterie"
+    pub name: String,
     /// library panel index
     pub panels: Vec<usize>,
     /// library battery index
@@ -139,88 +611,513 @@ pub struct Project {
     pub inverters: Vec<usize>,
     /// Specific yield (regional / time based)
     pub yield_kwh_kwp: f32,
-    /// How much you consume
-    pub consumption_kwh: f32,
-    /// how much you pay per kWh
-    pub price_kwh_eur_buy: f32,
-    /// How much you get per kWh sold
-    pub price_kwh_eur_sell: f32,
+    /// How much you consume per year
+    #[serde(rename = "consumption_kwh", with = "units::kilowatt_hours")]
+    pub consumption: Energy,
+    /// how much you pay per kWh, in the project currency
+    #[serde(
+        rename = "price_kwh_buy",
+        alias = "price_kwh_eur_buy",
+        with = "units::money_per_kwh"
+    )]
+    pub price_buy: EnergyPrice,
+    /// How much you get per kWh sold, in the project currency
+    #[serde(
+        rename = "price_kwh_sell",
+        alias = "price_kwh_eur_sell",
+        with = "units::money_per_kwh"
+    )]
+    pub price_sell: EnergyPrice,
     /// how much the panel deviates from right-angle to the sun. 0=facing sun
     pub panel_angle_deg: f32,
-    /// 0-180. how much the panel deviates from facing south. 0=facing south
-    pub panel_orientation: f32,
+    /// Compass direction the panels face, 0-360°: 90 = east, 180 = south, 270 = west
+    pub azimuth_deg: f32,
+    /// 0-180° deviation from south, from before east and west were told apart.
+    /// Converted by [`Project::migrate`].
+    #[serde(rename = "panel_orientation", skip_serializing)]
+    pub legacy_south_deviation: Option<f32>,
+    /// Latitude of the site, used for the course of the sun
+    pub latitude_deg: f32,
     /// interest rate for alternate investment (interest rate for deposit)
     pub interest_rate_deposit: f32,
     /// Flat fee for panel installation
-    pub price_installation_panels: f32,
+    #[serde(with = "units::money")]
+    pub price_installation_panels: Money,
     /// Flat fee for electricity installation
-    pub price_installation_electricity: f32,
+    #[serde(with = "units::money")]
+    pub price_installation_electricity: Money,
+    /// Years the system is expected to run, used for the net present value
+    pub lifetime_years: f32,
+    /// Roof area available for panels, 0 if unknown
+    #[serde(rename = "roof_area_m2", with = "units::square_meters")]
+    pub roof_area: Area,
+    /// Maximum power fed into the grid, 0 for no limit
+    #[serde(rename = "feed_in_limit_kw", with = "units::kilowatts")]
+    pub feed_in_limit: Power,
+    /// Which supplier quote the components are priced with
+    pub price_selection: PriceSelection,
+    /// Day the quotes are chosen for, today if `None`
+    pub price_date: Option<Date>,
+    /// Mounting material, cables, labour, ... from the library
+    pub cost_items: Vec<CostLine>,
+    /// Who the offer is for
+    pub customer: Customer,
+    /// VAT rates and income tax
+    pub tax: Tax,
+    /// Of the energy prices and installation fees, and everything calculated. Library
+    /// prices are converted.
+    pub currency: Currency,
+}
+
+/// Contact data printed on reports
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Customer {
+    pub name: String,
+    /// Street and city, one per line
+    pub address: String,
+    pub email: String,
+    pub phone: String,
+}
+
+/// A cost item used in a project
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct CostLine {
+    /// Library cost item index
+    pub item: usize,
+    /// Pieces, meters or hours. For items priced per module or kWp a factor, e.g. 2 roof
+    /// hooks per module.
+    pub quantity: f32,
+}
+
+/// One line of a bill of materials
+#[derive(Debug, Clone, PartialEq)]
+pub struct BomLine {
+    pub description: String,
+    pub quantity: f32,
+    /// e.g. "m"
+    pub unit: String,
+    /// Net
+    pub unit_price: Money,
+    /// Net
+    pub total: Money,
+    pub category: CostCategory,
+}
+
+impl BomLine {
+    fn new(
+        category: CostCategory,
+        description: String,
+        quantity: f32,
+        unit: String,
+        unit_price: Money,
+    ) -> Self {
+        Self {
+            description,
+            quantity,
+            unit,
+            unit_price,
+            total: unit_price * quantity,
+            category,
+        }
+    }
 }
 
 impl Default for Project {
     fn default() -> Self {
         Self {
+            name: tr!("scenario_n", number = 1),
             panels: Default::default(),
             batteries: Default::default(),
             inverters: Default::default(),
             yield_kwh_kwp: 1000.0,
-            consumption_kwh: 2500.0,
-            price_kwh_eur_buy: 0.4229,
-            price_kwh_eur_sell: 0.082,
+            consumption: Energy::from_kwh(2500.0),
+            price_buy: EnergyPrice::from_amount_per_kwh(0.4229),
+            price_sell: EnergyPrice::from_amount_per_kwh(0.082),
             panel_angle_deg: 0.0,
-            panel_orientation: 0.0,
+            azimuth_deg: 180.0,
+            legacy_south_deviation: None,
+            latitude_deg: 51.0,
             interest_rate_deposit: 0.042,
-            price_installation_electricity: 3000.,
-            price_installation_panels: 2000.,
+            price_installation_electricity: Money::from_amount(3000.),
+            price_installation_panels: Money::from_amount(2000.),
+            lifetime_years: 20.,
+            roof_area: Area::default(),
+            feed_in_limit: Power::default(),
+            price_selection: PriceSelection::default(),
+            price_date: None,
+            cost_items: Default::default(),
+            customer: Customer::default(),
+            tax: Tax::default(),
+            currency: Currency::default(),
         }
     }
 }
 
 impl Project {
+    /// Upgrades projects saved by older versions
+    pub fn migrate(&mut self) {
+        // the old value didn't say east or west, take it as west
+        if let Some(deviation) = self.legacy_south_deviation.take() {
+            self.azimuth_deg = (180. + deviation.clamp(0., 180.)) % 360.;
+        }
+    }
+
+    /// Signed deviation of the azimuth from facing the equator, -180..180°: negative is east,
+    /// positive west
+    pub fn azimuth_from_equator(&self) -> f32 {
+        panel_orientation::from_equator(self.azimuth_deg, self.latitude_deg)
+    }
+
+    /// Prices chosen by `price_selection` on `price_date`, in the currency of the project
+    pub fn pricing(&self, library: &Library) -> Pricing {
+        Pricing {
+            selection: self.price_selection,
+            day: self.price_date.unwrap_or_else(Date::today),
+            currency: self.currency.clone(),
+            rates: library.exchange_rates.clone(),
+        }
+    }
+
+    /// Components without an exchange rate are left out of the price, see
+    /// [`Project::missing_rates`]
     pub fn sum(&self, library: &Library) -> ProjectResult {
+        let pricing = self.pricing(library);
         let mut res = self
             .panels
             .iter()
-            .map(|id| library.panels.get(*id))
-            .filter_map(|x| x)
+            .filter_map(|id| library.panels.get(*id))
             .fold(ProjectResult::default(), |acc, p| ProjectResult {
-                energy_sum_wp: acc.energy_sum_wp + p.energy_wp,
-                price_sum: acc.price_sum + p.price_eur,
-                area_sum: acc.area_sum + p.size_cm.x * p.size_cm.y,
+                peak_power: acc.peak_power + p.peak_power,
+                price_sum: acc.price_sum + p.price(&pricing).unwrap_or_default(),
+                area: acc.area + p.area(),
             });
 
         res.price_sum += self
-            .batteries
-            .iter()
-            .map(|id| library.batteries.get(*id))
-            .filter_map(|x| x)
-            .map(|b| b.price_eur)
-            .sum::<f32>();
+            .batteries(library)
+            .filter_map(|b| b.bank_price(&pricing))
+            .sum::<Money>();
 
         res.price_sum += self
             .inverters
             .iter()
-            .map(|id| library.inverters.get(*id))
-            .filter_map(|x| x)
-            .map(|b| b.price_eur)
-            .sum::<f32>();
+            .filter_map(|id| library.inverters.get(*id))
+            .filter_map(|i| i.price(&pricing))
+            .sum::<Money>();
 
-        res.price_sum += self.price_installation_electricity;
-        res.price_sum += self.price_installation_panels;
+        res.price_sum += self
+            .cost_lines(library, res.peak_power)
+            .filter_map(|(item, quantity)| Some(item.price(&pricing)? * quantity))
+            .sum::<Money>();
+
+        res.price_sum += net(
+            self.price_installation_electricity + self.price_installation_panels,
+            self.tax.installation_vat(),
+        );
 
         res
     }
+
+    /// The cost items of this project with their total quantity
+    fn cost_lines<'a>(
+        &'a self,
+        library: &'a Library,
+        peak_power: Power,
+    ) -> impl Iterator<Item = (&'a CostItem, f32)> {
+        self.cost_items.iter().filter_map(move |line| {
+            let item = library.cost_items.get(line.item)?;
+            let quantity = match item.unit {
+                CostUnit::Flat => 1.,
+                unit => line.quantity * unit.base(self.panels.len(), peak_power.kw()),
+            };
+            Some((item, quantity))
+        })
+    }
+
+    /// Everything the project costs, line by line. The totals add up to [`Project::sum`], so
+    /// components without an exchange rate are left out.
+    pub fn bill_of_materials(&self, library: &Library) -> Vec<BomLine> {
+        let pricing = self.pricing(library);
+        let pieces = tr!("pieces_short");
+        let name = |brand: &str, model: &str| format!("{brand} {model}").trim().to_string();
+        // the same component listed several times becomes one line
+        let count = |ids: &[usize]| {
+            let mut counts: Vec<(usize, usize)> = vec![];
+            for id in ids {
+                match counts.iter_mut().find(|(i, _)| i == id) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((*id, 1)),
+                }
+            }
+            counts
+        };
+
+        let mut lines = vec![];
+        for (id, n) in count(&self.panels) {
+            if let Some((p, price)) = library
+                .panels
+                .get(id)
+                .and_then(|p| Some((p, p.price(&pricing)?)))
+            {
+                let description = name(&p.brand, &p.model);
+                lines.push(BomLine::new(
+                    CostCategory::Panels,
+                    description,
+                    n as f32,
+                    pieces.clone(),
+                    price,
+                ));
+            }
+        }
+        for (id, n) in count(&self.inverters) {
+            if let Some((i, price)) = library
+                .inverters
+                .get(id)
+                .and_then(|i| Some((i, i.price(&pricing)?)))
+            {
+                let description = name(&i.brand, &i.model);
+                lines.push(BomLine::new(
+                    CostCategory::Inverters,
+                    description,
+                    n as f32,
+                    pieces.clone(),
+                    price,
+                ));
+            }
+        }
+        for (id, n) in count(&self.batteries) {
+            if let Some((b, price)) = library
+                .batteries
+                .get(id)
+                .and_then(|b| Some((b, b.price(&pricing)?)))
+            {
+                lines.push(BomLine::new(
+                    CostCategory::Batteries,
+                    name(&b.brand, &b.model),
+                    (n as u32 * b.modules()) as f32,
+                    pieces.clone(),
+                    price,
+                ));
+            }
+        }
+        let peak_power = self.sum(library).peak_power;
+        for (item, quantity) in self.cost_lines(library, peak_power) {
+            if let Some(price) = item.price(&pricing) {
+                lines.push(BomLine::new(
+                    CostCategory::Material,
+                    name(&item.brand, &item.model),
+                    quantity,
+                    item.unit.symbol(),
+                    price,
+                ));
+            }
+        }
+        for (description, price) in [
+            (
+                tr!("installation_cost_panels"),
+                self.price_installation_panels,
+            ),
+            (
+                tr!("installation_cost_electrics"),
+                self.price_installation_electricity,
+            ),
+        ] {
+            if price.amount() != 0. {
+                lines.push(BomLine::new(
+                    CostCategory::Installation,
+                    description,
+                    1.,
+                    tr!("flat_short"),
+                    net(price, self.tax.installation_vat()),
+                ));
+            }
+        }
+        lines
+    }
+
+    /// Currencies of the components that can't be converted into the project currency, and
+    /// are therefore left out of the price
+    pub fn missing_rates(&self, library: &Library) -> Vec<Currency> {
+        let pricing = self.pricing(library);
+        let mut missing: Vec<Currency> = vec![];
+        let unpriced = (self.panels.iter())
+            .filter_map(|id| library.panels.get(*id))
+            .filter(|p| p.price(&pricing).is_none())
+            .map(|p| &p.currency)
+            .chain(
+                (self.inverters.iter())
+                    .filter_map(|id| library.inverters.get(*id))
+                    .filter(|i| i.price(&pricing).is_none())
+                    .map(|i| &i.currency),
+            )
+            .chain(
+                self.batteries(library)
+                    .filter(|b| b.price(&pricing).is_none())
+                    .map(|b| &b.currency),
+            )
+            .chain(
+                (self.cost_items.iter())
+                    .filter_map(|line| library.cost_items.get(line.item))
+                    .filter(|c| c.price(&pricing).is_none())
+                    .map(|c| &c.currency),
+            );
+        for currency in unpriced {
+            if !missing.contains(currency) {
+                missing.push(currency.clone());
+            }
+        }
+        missing
+    }
+
+    /// The battery banks of this project
+    pub fn batteries<'a>(&'a self, library: &'a Library) -> impl Iterator<Item = &'a Battery> {
+        self.batteries
+            .iter()
+            .filter_map(|id| library.batteries.get(*id))
+    }
+
+    /// All battery banks combined. The efficiency is weighted by usable capacity.
+    pub fn storage(&self, library: &Library) -> Storage {
+        let capacity = self
+            .batteries(library)
+            .map(|b| b.bank_usable())
+            .sum::<Energy>();
+        let weighted_efficiency = self
+            .batteries(library)
+            .map(|b| b.bank_usable() * b.efficiency)
+            .sum::<Energy>();
+        Storage {
+            capacity_kwh: capacity.kwh(),
+            max_charge_kw: self
+                .batteries(library)
+                .map(|b| b.bank_max_charge())
+                .sum::<Power>()
+                .kw(),
+            max_discharge_kw: self
+                .batteries(library)
+                .map(|b| b.bank_max_discharge())
+                .sum::<Power>()
+                .kw(),
+            efficiency: if capacity.kwh() > 0. {
+                weighted_efficiency / capacity
+            } else {
+                1.
+            },
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
-#[serde(default)]
+#[derive(Debug, Default)]
 pub struct ProjectResult {
-    pub energy_sum_wp: f32,
-    pub price_sum: f32,
-    pub area_sum: f32,
+    pub peak_power: Power,
+    /// Net
+    pub price_sum: Money,
+    pub area: Area,
+}
+
+#[test]
+fn battery_bank() {
+    let mut library: Library = serde_json::from_str(
+        r#"{ "panels": [], "batteries": [{ "price_eur": 100, "energy_ahr": 200, "voltage": 12 }] }"#,
+    )
+    .unwrap();
+    library.migrate();
+    // prices were stored as euros before there were currencies
+    assert_eq!(library.batteries[0].price.amount(), 100.);
+    assert!(serde_json::to_string(&library)
+        .unwrap()
+        .contains(r#""price":100.0"#));
+    let lead_acid = &library.batteries[0];
+    assert_eq!(lead_acid.chemistry, Chemistry::LeadAcid);
+    assert_eq!(lead_acid.nominal.kwh(), 2.4);
+    assert_eq!(lead_acid.usable.kwh(), 1.2);
+
+    let bank = &Library::default().batteries[1];
+    assert_eq!(bank.modules(), 2);
+    assert_eq!(bank.bank_usable().kwh(), 5.12);
+    assert_eq!(bank.bank_voltage().v(), 204.8);
+    // limited by the cycles, not by the calendar
+    assert_eq!(bank.life_years(600.), 10.);
+    assert_eq!(bank.life_years(0.), 15.);
+}
+
+#[test]
+fn bill_of_materials() {
+    let library = Library {
+        cost_items: vec![
+            CostItem {
+                model: "Roof hook".into(),
+                price: Money::from_amount(8.),
+                unit: CostUnit::Module,
+                ..Default::default()
+            },
+            CostItem {
+                model: "Scaffolding".into(),
+                price: Money::from_amount(714.),
+                price_gross: true,
+                vat_rate: 0.19,
+                unit: CostUnit::Flat,
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    let project = Project {
+        panels: vec![0; 10],
+        inverters: vec![0],
+        batteries: vec![1, 1],
+        cost_items: vec![
+            CostLine {
+                item: 0,
+                quantity: 2.,
+            },
+            CostLine {
+                item: 1,
+                quantity: 3.,
+            },
+        ],
+        ..Default::default()
+    };
+    let bom = project.bill_of_materials(&library);
+    let mut polish = project.clone();
+    polish.tax.standard_vat_rate = 0.23;
+    assert_eq!(polish.bill_of_materials(&library), bom);
+    // panels, inverter, battery, 2 cost items and 2 installation fees
+    assert_eq!(bom.len(), 7);
+    assert_eq!(bom[0].quantity, 10.);
+    // both banks of the same battery on one line
+    assert_eq!(bom[2].category, CostCategory::Batteries);
+    assert_eq!(bom[2].quantity, 2. * library.batteries[1].modules() as f32);
+    assert_eq!(bom[3].total.amount(), 160.);
+    // the gross price without the VAT it includes, whatever the rate of the project
+    assert!((bom[4].total.amount() - 600.).abs() < 0.01);
+    assert_eq!(bom[4].category, CostCategory::Material);
+    let total = bom.iter().map(|l| l.total).sum::<Money>();
+    assert!((total.amount() - project.sum(&library).price_sum.amount()).abs() < 0.01);
+    assert!(project.missing_rates(&library).is_empty());
+
+    // without an exchange rate the hooks are left out
+    let mut library = library;
+    library.cost_items[0].currency = Currency("PLN".into());
+    assert_eq!(project.bill_of_materials(&library).len(), 6);
+    let sum = project.sum(&library).price_sum;
+    assert!((total.amount() - 160. - sum.amount()).abs() < 0.01);
+    assert_eq!(project.missing_rates(&library), [Currency("PLN".into())]);
 }
 
-/// simple funciton to determine compound interest as alternative investment
-pub fn compound_interest(start_capital: f32, interest: f32, years: f32) -> f32 {
-    start_capital * (1.0 + interest).powf(years)
+#[test]
+fn orientation() {
+    let mut project: Project = serde_json::from_str(r#"{ "panel_orientation": 90 }"#).unwrap();
+    project.migrate();
+    assert_eq!(project.azimuth_deg, 270.);
+    assert_eq!(project.azimuth_from_equator(), 90.);
+    let east = Project {
+        azimuth_deg: 100.,
+        ..Default::default()
+    };
+    assert_eq!(east.azimuth_from_equator(), -80.);
+    assert_eq!(Project::default().azimuth_from_equator(), 0.);
+    assert!(!serde_json::to_string(&project)
+        .unwrap()
+        .contains("panel_orientation"));
 }
@@ -0,0 +1,238 @@
+//! Currencies and exchange rates. Library prices can be in any currency; a project is
+//! calculated and shown in its own one, converted with the rates kept in the library.
+//! The rates are maintained by hand, nothing is fetched.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{quotes::Date, tr, units::Money};
+
+/// ISO 4217 code, e.g. "CHF"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct Currency(pub String);
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::eur()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Currency {
+    /// The base of the exchange rates
+    pub fn eur() -> Self {
+        Self("EUR".into())
+    }
+
+    pub fn is_eur(&self) -> bool {
+        self.0 == "EUR"
+    }
+
+    /// "€", or the code if there is no common sign that the PDF fonts can show
+    pub fn symbol(&self) -> &str {
+        match self.0.as_str() {
+            "EUR" => "€",
+            "USD" => "$",
+            "GBP" => "£",
+            code => code,
+        }
+    }
+}
+
+/// How many units of `currency` one euro buys, as of `date`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ExchangeRate {
+    pub currency: Currency,
+    pub per_eur: f32,
+    pub date: Date,
+}
+
+impl Default for ExchangeRate {
+    fn default() -> Self {
+        Self {
+            currency: Currency(String::new()),
+            per_eur: 1.,
+            date: Date::today(),
+        }
+    }
+}
+
+/// The rates of all currencies used besides EUR
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct ExchangeRates(pub Vec<ExchangeRate>);
+
+impl ExchangeRates {
+    /// Units of `currency` per euro, if known
+    pub fn per_eur(&self, currency: &Currency) -> Option<f32> {
+        if currency.is_eur() {
+            return Some(1.);
+        }
+        self.0
+            .iter()
+            .find(|r| r.currency == *currency && r.per_eur > 0.)
+            .map(|r| r.per_eur)
+    }
+
+    /// What one unit of `from` is worth in `to`
+    pub fn factor(&self, from: &Currency, to: &Currency) -> Option<f32> {
+        if from == to {
+            return Some(1.);
+        }
+        Some(self.per_eur(to)? / self.per_eur(from)?)
+    }
+
+    pub fn convert(&self, money: Money, from: &Currency, to: &Currency) -> Option<Money> {
+        self.factor(from, to).map(|factor| money * factor)
+    }
+
+    /// EUR and all currencies with a rate
+    pub fn currencies(&self) -> Vec<Currency> {
+        let mut currencies = vec![Currency::eur()];
+        for rate in &self.0 {
+            if !rate.currency.0.is_empty() && !currencies.contains(&rate.currency) {
+                currencies.push(rate.currency.clone());
+            }
+        }
+        currencies
+    }
+
+    /// Adds the rates of `theirs`, keeping the newer one of a currency known to both.
+    /// Returns the number of rates added or replaced.
+    pub fn merge(&mut self, theirs: ExchangeRates) -> usize {
+        let mut changed = 0;
+        for rate in theirs.0 {
+            match self.0.iter_mut().find(|r| r.currency == rate.currency) {
+                Some(mine) if mine.date < rate.date => {
+                    *mine = rate;
+                    changed += 1;
+                }
+                Some(_) => {}
+                None => {
+                    self.0.push(rate);
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Edits the code of the currency of a price
+pub fn currency_edit(ui: &mut egui::Ui, currency: &mut Currency) -> egui::Response {
+    ui.add(
+        egui::TextEdit::singleline(&mut currency.0)
+            .hint_text("EUR")
+            .desired_width(32.),
+    )
+}
+
+/// Picks one of the currencies that can be converted
+pub fn currency_combo(ui: &mut egui::Ui, id: &str, currency: &mut Currency, rates: &ExchangeRates) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(currency.to_string())
+        .width(60.)
+        .show_ui(ui, |ui| {
+            for option in rates.currencies() {
+                let label = option.to_string();
+                ui.selectable_value(currency, option, label);
+            }
+        });
+}
+
+/// Editor for the exchange rates
+pub fn exchange_rates_ui(ui: &mut egui::Ui, rates: &mut ExchangeRates) {
+    let mut delete = None;
+    egui::Grid::new("exchange_rates")
+        .num_columns(4)
+        .show(ui, |ui| {
+            for (i, rate) in rates.0.iter_mut().enumerate() {
+                ui.label("1 EUR =");
+                ui.add(
+                    egui::DragValue::new(&mut rate.per_eur)
+                        .clamp_range(0.0..=f32::MAX)
+                        .speed(0.001)
+                        .max_decimals(4),
+                );
+                ui.add(
+                    egui::TextEdit::singleline(&mut rate.currency.0)
+                        .hint_text("CHF")
+                        .desired_width(40.),
+                );
+                ui.horizontal(|ui| {
+                    ui.label(tr!("as_of", date = rate.date));
+                    if ui
+                        .small_button(egui_phosphor::regular::CALENDAR_CHECK)
+                        .clicked()
+                    {
+                        rate.date = Date::today();
+                    }
+                    if ui
+                        .small_button(egui_phosphor::regular::TRASH_SIMPLE)
+                        .clicked()
+                    {
+                        delete = Some(i);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+    if let Some(i) = delete {
+        rates.0.remove(i);
+    }
+    if ui
+        .small_button(format!(
+            "{} {}",
+            egui_phosphor::regular::PLUS,
+            tr!("add_exchange_rate")
+        ))
+        .clicked()
+    {
+        rates.0.push(ExchangeRate::default());
+    }
+}
+
+#[test]
+fn exchange_rates() {
+    let chf = Currency("CHF".into());
+    let pln = Currency("PLN".into());
+    let mut rates = ExchangeRates(vec![
+        ExchangeRate {
+            currency: chf.clone(),
+            per_eur: 0.95,
+            date: Date::new(2024, 1, 1),
+        },
+        ExchangeRate {
+            currency: pln.clone(),
+            per_eur: 4.3,
+            date: Date::new(2024, 1, 1),
+        },
+    ]);
+    let eur = Currency::eur();
+    assert_eq!(rates.factor(&eur, &eur), Some(1.));
+    assert_eq!(
+        rates.convert(Money::from_amount(100.), &eur, &chf),
+        Some(Money::from_amount(95.))
+    );
+    let zloty = rates.convert(Money::from_amount(95.), &chf, &pln).unwrap();
+    assert!((zloty.amount() - 430.).abs() < 0.01);
+    assert_eq!(rates.factor(&Currency("USD".into()), &eur), None);
+    assert_eq!(rates.currencies(), [eur, chf.clone(), pln]);
+
+    let newer = ExchangeRates(vec![ExchangeRate {
+        currency: chf.clone(),
+        per_eur: 0.93,
+        date: Date::new(2024, 6, 1),
+    }]);
+    assert_eq!(rates.merge(newer.clone()), 1);
+    assert_eq!(rates.per_eur(&chf), Some(0.93));
+    assert_eq!(rates.merge(newer), 0);
+}
@@ -0,0 +1,115 @@
+//! Display formatting for quantities, following the current locale.
+//!
+//! Every quantity has one rounding rule, so the same value looks the same everywhere:
+//! kWh and money in whole units, kWp with two decimals, m² and years with one, and
+//! percentages as whole numbers.
+
+use crate::{currency::Currency, translation::locale};
+
+/// Thousands and decimal separator
+fn separators(locale: &str) -> (char, char) {
+    match locale {
+        "de" => ('.', ','),
+        _ => (',', '.'),
+    }
+}
+
+/// A number with grouped thousands, e.g. "12.345,6" in German
+pub fn number_in(value: f32, decimals: usize, locale: &str) -> String {
+    if !value.is_finite() {
+        return "–".into();
+    }
+    let (thousands, decimal) = separators(locale);
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+
+    let mut out = String::new();
+    // "-0" after rounding is just "0"
+    if value < 0. && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        out.push('-');
+    }
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            out.push(thousands);
+        }
+        out.push(digit);
+    }
+    if !fraction.is_empty() {
+        out.push(decimal);
+        out.push_str(fraction);
+    }
+    out
+}
+
+pub fn number(value: f32, decimals: usize) -> String {
+    number_in(value, decimals, &locale())
+}
+
+/// `value` with the `symbol` of its currency, which English puts in front if it is a sign
+/// like "€" rather than a code like "CHF"
+pub fn money_in(value: f32, decimals: usize, locale: &str, symbol: &str) -> String {
+    let amount = number_in(value, decimals, locale);
+    match locale {
+        "en" if symbol.chars().count() == 1 => match amount.strip_prefix('-') {
+            Some(amount) => format!("-{symbol}{amount}"),
+            None => format!("{symbol}{amount}"),
+        },
+        _ => format!("{amount} {symbol}"),
+    }
+}
+
+/// Whole units of `currency`, for totals
+pub fn money(value: f32, currency: &Currency) -> String {
+    money_in(value, 0, &locale(), currency.symbol())
+}
+
+/// With cents of `currency`, for prices of single items
+pub fn price(value: f32, currency: &Currency) -> String {
+    money_in(value, 2, &locale(), currency.symbol())
+}
+
+pub fn kwh(value: f32) -> String {
+    format!("{} kWh", number(value, 0))
+}
+
+pub fn kwp(value: f32) -> String {
+    format!("{} kWp", number(value, 2))
+}
+
+pub fn area(m2: f32) -> String {
+    format!("{} m²", number(m2, 1))
+}
+
+pub fn years(value: f32) -> String {
+    number(value, 1)
+}
+
+pub fn percent_in(ratio: f32, locale: &str) -> String {
+    let value = number_in(ratio * 100., 0, locale);
+    match locale {
+        "de" => format!("{value} %"),
+        _ => format!("{value}%"),
+    }
+}
+
+/// `ratio` from 0-1 as percent
+pub fn percent(ratio: f32) -> String {
+    percent_in(ratio, &locale())
+}
+
+#[test]
+fn formatting() {
+    assert_eq!(number_in(1_234_567.5, 1, "de"), "1.234.567,5");
+    assert_eq!(number_in(1_234_567.5, 1, "en"), "1,234,567.5");
+    assert_eq!(number_in(999.6, 0, "en"), "1,000");
+    assert_eq!(number_in(-1234., 0, "de"), "-1.234");
+    assert_eq!(number_in(-0.2, 0, "de"), "0");
+    assert_eq!(number_in(12., 1, "de"), "12,0");
+    assert_eq!(number_in(f32::INFINITY, 1, "de"), "–");
+    assert_eq!(money_in(7060., 0, "de", "€"), "7.060 €");
+    assert_eq!(money_in(7060., 0, "en", "€"), "€7,060");
+    assert_eq!(money_in(-512.5, 2, "en", "€"), "-€512.50");
+    assert_eq!(money_in(7060., 0, "en", "CHF"), "7,060 CHF");
+    assert_eq!(percent_in(0.626, "de"), "63 %");
+    assert_eq!(percent_in(0.626, "en"), "63%");
+}
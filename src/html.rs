@@ -0,0 +1,188 @@
+//! Renders a [`Report`] as a single HTML file with inline SVG charts, to open in any browser.
+
+use std::fmt::Write;
+
+use egui::Color32;
+
+use crate::{
+    format,
+    report::{Block, Chart, Report, Table},
+};
+
+const STYLE: &str = "
+body { font-family: Helvetica, Arial, sans-serif; font-size: 14px; color: #222;
+  max-width: 800px; margin: 2em auto; padding: 0 1em; }
+h1 { margin-bottom: 0; }
+.subtitle { color: #666; margin-top: 0.2em; }
+h2 { border-bottom: 1px solid #999; padding-bottom: 0.2em; margin-top: 2em; }
+table { border-collapse: collapse; width: 100%; margin: 0.5em 0; }
+th, td { padding: 0.2em 0.4em; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+thead th { border-bottom: 1px solid #222; }
+tbody tr:nth-child(even) { background: #f0f0f0; }
+tfoot td { font-weight: bold; }
+tfoot tr:first-child td { border-top: 1px solid #222; }
+table.facts th, table.facts td { text-align: left; vertical-align: top; }
+table.facts th { width: 12em; }
+svg { display: block; margin: 0.5em 0 1em; }
+footer { color: #777; font-size: 12px; margin-top: 3em; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn facts(out: &mut String, facts: &[(String, String)]) {
+    out.push_str("<table class=\"facts\">\n");
+    for (label, value) in facts {
+        let value = escape(value).replace('\n', "<br>");
+        _ = writeln!(out, "<tr><th>{}</th><td>{value}</td></tr>", escape(label));
+    }
+    out.push_str("</table>\n");
+}
+
+fn table(out: &mut String, table: &Table) {
+    let row = |out: &mut String, cells: &[String], cell: &str| {
+        out.push_str("<tr>");
+        for text in cells {
+            _ = write!(out, "<{cell}>{}</{cell}>", escape(text));
+        }
+        out.push_str("</tr>\n");
+    };
+    out.push_str("<table>\n<thead>\n");
+    row(out, &table.header, "th");
+    out.push_str("</thead>\n<tbody>\n");
+    for cells in &table.rows {
+        row(out, cells, "td");
+    }
+    out.push_str("</tbody>\n");
+    if !table.totals.is_empty() {
+        out.push_str("<tfoot>\n");
+        for cells in &table.totals {
+            row(out, cells, "td");
+        }
+        out.push_str("</tfoot>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+/// Grouped bars as SVG, laid out like the PDF charts
+fn chart(out: &mut String, chart: &Chart) {
+    let (width, height) = (760., 260.);
+    let (left, right, top, bottom) = (50., width - 10., 30., height - 25.);
+    let (step, steps) = chart.axis();
+    let scale = (bottom - top) / (step * steps as f32);
+
+    _ = writeln!(
+        out,
+        "<h3>{} ({})</h3>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" \
+         height=\"{height}\" viewBox=\"0 0 {width} {height}\" font-size=\"11\">",
+        escape(&chart.title),
+        escape(&chart.unit)
+    );
+    let mut x = left;
+    for series in &chart.series {
+        _ = writeln!(
+            out,
+            "<rect x=\"{x}\" y=\"8\" width=\"10\" height=\"10\" fill=\"{}\"/>\
+             <text x=\"{}\" y=\"17\">{}</text>",
+            hex(series.color),
+            x + 14.,
+            escape(&series.name)
+        );
+        x += 30. + 6. * series.name.chars().count() as f32;
+    }
+    for i in 0..=steps {
+        let value = step * i as f32;
+        let y = bottom - value * scale;
+        let stroke = if i == 0 { "#222" } else { "#ddd" };
+        _ = writeln!(
+            out,
+            "<line x1=\"{left}\" y1=\"{y}\" x2=\"{right}\" y2=\"{y}\" stroke=\"{stroke}\"/>\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            left - 4.,
+            y + 4.,
+            format::number(value, 0)
+        );
+    }
+    let group_width = (right - left) / chart.labels.len().max(1) as f32;
+    let bar_width = group_width * 0.8 / chart.series.len().max(1) as f32;
+    for (g, label) in chart.labels.iter().enumerate() {
+        let group_left = left + g as f32 * group_width + group_width * 0.1;
+        for (s, series) in chart.series.iter().enumerate() {
+            let value = series.values.get(g).copied().unwrap_or(0.).max(0.);
+            _ = writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{bar_width}\" height=\"{}\" fill=\"{}\">\
+                 <title>{}: {}</title></rect>",
+                group_left + s as f32 * bar_width,
+                bottom - value * scale,
+                value * scale,
+                hex(series.color),
+                escape(&series.name),
+                format::number(value, 0)
+            );
+        }
+        _ = writeln!(
+            out,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            left + (g as f32 + 0.5) * group_width,
+            bottom + 16.,
+            escape(label)
+        );
+    }
+    out.push_str("</svg>\n");
+}
+
+/// The report as HTML document
+pub fn render(report: &Report) -> String {
+    let mut out = String::new();
+    _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{} – {}</title>\n<style>{STYLE}</style>\n</head>\n<body>",
+        escape(&report.title),
+        escape(&report.subtitle)
+    );
+    _ = writeln!(
+        out,
+        "<h1>{}</h1>\n<p class=\"subtitle\">{} – {}</p>",
+        escape(&report.title),
+        escape(&report.subtitle),
+        report.date
+    );
+    for section in &report.sections {
+        _ = writeln!(out, "<h2>{}</h2>", escape(&section.heading));
+        for block in &section.blocks {
+            match block {
+                Block::Text(text) => _ = writeln!(out, "<p>{}</p>", escape(text)),
+                Block::Facts(list) => facts(&mut out, list),
+                Block::Table(t) => table(&mut out, t),
+                Block::Chart(c) => chart(&mut out, c),
+            }
+        }
+    }
+    _ = writeln!(
+        out,
+        "<footer>{}</footer>\n</body>\n</html>",
+        escape(&report.disclaimer)
+    );
+    out
+}
+
+#[test]
+fn html() {
+    let html = render(&crate::report::sample());
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("Mit &lt;Speicher&gt;"));
+    assert!(html.contains("Heidestraße 17<br>51147 Köln"));
+    assert_eq!(html.matches("<svg").count(), 2);
+    assert!(html.trim_end().ends_with("</html>"));
+}
@@ -0,0 +1,410 @@
+//! Imports panels, inverters, batteries and cost items from CSV files into the library: the CEC module
+//! and inverter databases as distributed with SAM and pvlib, and our own template
+//! (see `library_template.csv`). The format is told by the header.
+//!
+//! Items are matched by brand and model, so importing a newer database updates the
+//! existing entries instead of adding them twice.
+
+use std::{collections::HashMap, io::Read};
+
+use egui::Vec2;
+use serde::Deserialize;
+
+use crate::{
+    catalogue::{index_of, stack, Product},
+    components::{Battery, Chemistry, CostItem, CostUnit, Inverter, Library, Panel},
+    currency::Currency,
+    units::{Energy, Money, Power, Voltage},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    CecModules,
+    CecInverters,
+    Template,
+}
+
+impl Source {
+    fn detect(headers: &csv::StringRecord) -> Option<Self> {
+        let has = |name: &str| headers.iter().any(|h| h.trim() == name);
+        if has("kind") && has("brand") && has("model") {
+            Some(Source::Template)
+        } else if has("Name") && has("Manufacturer") && has("STC") {
+            Some(Source::CecModules)
+        } else if has("Name") && has("Paco") && has("Pdco") {
+            Some(Source::CecInverters)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportReport {
+    pub source: Source,
+    pub added: usize,
+    pub updated: usize,
+    /// Rows that don't describe a product, e.g. the unit rows of the CEC files
+    pub skipped: usize,
+}
+
+/// Adds `item` or replaces the item with the same brand and model. The databases carry no
+/// prices, so a known price is kept if the new one is 0, with its currency and whether it
+/// includes VAT, and so are the quotes. Imported items are personal, so items of other
+/// layers are overridden rather than replaced.
+fn upsert<T: Product>(
+    items: &mut Vec<T>,
+    index: &mut HashMap<(String, String), usize>,
+    mut item: T,
+    report: &mut ImportReport,
+) {
+    match index.get(&item.key()) {
+        Some(&i) => {
+            if item.list_price().amount() <= 0. {
+                item.take_list_price(&items[i]);
+            }
+            if item.quotes().is_empty() {
+                *item.quotes_mut() = items[i].quotes().to_vec();
+            }
+            stack(&mut items[i], item);
+            report.updated += 1;
+        }
+        None => {
+            index.insert(item.key(), items.len());
+            items.push(item);
+            report.added += 1;
+        }
+    }
+}
+
+/// A row of the template. Columns that don't apply to the kind stay empty.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TemplateRow {
+    kind: String,
+    brand: String,
+    model: String,
+    #[serde(alias = "price_eur")]
+    price: Option<f32>,
+    /// Of the price, EUR if empty
+    currency: Option<String>,
+    /// Whether the price includes VAT: yes or no, no if empty
+    price_gross: Option<String>,
+    /// In %, the VAT rate a gross price includes
+    vat_rate: Option<f32>,
+    power_w: Option<f32>,
+    width_cm: Option<f32>,
+    height_cm: Option<f32>,
+    max_pv_kw: Option<f32>,
+    ac_kw: Option<f32>,
+    phases: Option<u8>,
+    chemistry: Option<String>,
+    nominal_kwh: Option<f32>,
+    usable_kwh: Option<f32>,
+    voltage_v: Option<f32>,
+    max_charge_kw: Option<f32>,
+    max_discharge_kw: Option<f32>,
+    /// What the price of a cost item refers to
+    unit: Option<String>,
+}
+
+fn chemistry(name: &str) -> Option<Chemistry> {
+    match name.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+        "lfp" | "lifepo4" => Some(Chemistry::Lfp),
+        "nmc" => Some(Chemistry::Nmc),
+        "lead_acid" | "agm" | "gel" => Some(Chemistry::LeadAcid),
+        _ => None,
+    }
+}
+
+fn yes_no(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "yes" | "true" | "1" => Some(true),
+        "no" | "false" | "0" | "" => Some(false),
+        _ => None,
+    }
+}
+
+fn cost_unit(name: &str) -> Option<CostUnit> {
+    match name.trim().to_lowercase().as_str() {
+        "piece" => Some(CostUnit::Piece),
+        "module" => Some(CostUnit::Module),
+        "kwp" => Some(CostUnit::Kwp),
+        "meter" | "m" => Some(CostUnit::Meter),
+        "hour" | "h" => Some(CostUnit::Hour),
+        "flat" => Some(CostUnit::Flat),
+        _ => None,
+    }
+}
+
+enum Item {
+    Panel(Panel),
+    Inverter(Inverter),
+    Battery(Battery),
+    CostItem(CostItem),
+}
+
+impl TemplateRow {
+    fn item(self) -> Option<Item> {
+        if self.brand.trim().is_empty() && self.model.trim().is_empty() {
+            return None;
+        }
+        let price = Money::from_amount(self.price.unwrap_or_default());
+        let currency = match self.currency.as_deref().map(str::trim) {
+            Some(code) if !code.is_empty() => Currency(code.to_uppercase()),
+            _ => Currency::default(),
+        };
+        let price_gross = yes_no(self.price_gross.as_deref().unwrap_or_default())?;
+        let vat_rate = if price_gross {
+            self.vat_rate? / 100.
+        } else {
+            0.
+        };
+        match self.kind.trim().to_lowercase().as_str() {
+            "panel" => Some(Item::Panel(Panel {
+                brand: self.brand,
+                model: self.model,
+                size_cm: Vec2::new(self.width_cm?, self.height_cm?),
+                price,
+                price_gross,
+                vat_rate,
+                currency,
+                peak_power: Power::from_w(self.power_w?),
+                ..Default::default()
+            })),
+            "inverter" => {
+                let ac_power = Power::from_kw(self.ac_kw?);
+                Some(Item::Inverter(Inverter {
+                    brand: self.brand,
+                    model: self.model,
+                    price,
+                    price_gross,
+                    vat_rate,
+                    currency,
+                    max_pv_power: self.max_pv_kw.map_or(ac_power, Power::from_kw),
+                    ac_power,
+                    phases: self.phases.unwrap_or_default(),
+                    ..Default::default()
+                }))
+            }
+            "battery" => {
+                let chemistry = match self.chemistry {
+                    Some(name) => chemistry(&name)?,
+                    None => Chemistry::default(),
+                };
+                let nominal = Energy::from_kwh(self.nominal_kwh?);
+                // 0.5 C unless given
+                let max_charge = self
+                    .max_charge_kw
+                    .map_or(Power::from_kw(nominal.kwh() * 0.5), Power::from_kw);
+                Some(Item::Battery(Battery {
+                    brand: self.brand,
+                    model: self.model,
+                    price,
+                    price_gross,
+                    vat_rate,
+                    currency,
+                    chemistry,
+                    nominal,
+                    usable: self
+                        .usable_kwh
+                        .map_or(nominal * chemistry.depth_of_discharge(), Energy::from_kwh),
+                    voltage: Voltage::from_v(self.voltage_v.unwrap_or_default()),
+                    max_charge,
+                    max_discharge: self.max_discharge_kw.map_or(max_charge, Power::from_kw),
+                    efficiency: chemistry.efficiency(),
+                    cycles: chemistry.cycles(),
+                    calendar_life_years: chemistry.calendar_life_years(),
+                    ..Default::default()
+                }))
+            }
+            "cost" => Some(Item::CostItem(CostItem {
+                brand: self.brand,
+                model: self.model,
+                price,
+                price_gross,
+                vat_rate,
+                currency,
+                unit: match self.unit {
+                    Some(name) => cost_unit(&name)?,
+                    None => CostUnit::default(),
+                },
+                ..Default::default()
+            })),
+            _ => None,
+        }
+    }
+}
+
+/// Value of the column named `name` as a number
+fn number(record: &csv::StringRecord, columns: &HashMap<String, usize>, name: &str) -> Option<f32> {
+    record
+        .get(*columns.get(name)?)?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|v: &f32| v.is_finite())
+}
+
+fn text<'a>(
+    record: &'a csv::StringRecord,
+    columns: &HashMap<String, usize>,
+    name: &str,
+) -> Option<&'a str> {
+    record
+        .get(*columns.get(name)?)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+/// A CEC module, e.g. Name "Aleo Solarwerke LEO 400", Manufacturer "Aleo Solarwerke".
+/// Length and width are in m, STC is the peak power in W.
+fn cec_module(record: &csv::StringRecord, columns: &HashMap<String, usize>) -> Option<Panel> {
+    let name = text(record, columns, "Name")?;
+    let brand = text(record, columns, "Manufacturer")?;
+    let model = name.strip_prefix(brand).unwrap_or(name).trim();
+    let length = number(record, columns, "Length").unwrap_or_default();
+    let width = number(record, columns, "Width").unwrap_or_default();
+    Some(Panel {
+        brand: brand.into(),
+        model: if model.is_empty() { name } else { model }.into(),
+        size_cm: Vec2::new(width, length) * 100.,
+        peak_power: Power::from_w(number(record, columns, "STC").filter(|w| *w > 0.)?),
+        ..Default::default()
+    })
+}
+
+/// A CEC inverter, e.g. Name "SMA America: SB5.0-1SP-US-40 [240V]". Paco is the rated AC
+/// power and Pdco the DC power it is reached at, both in W.
+fn cec_inverter(record: &csv::StringRecord, columns: &HashMap<String, usize>) -> Option<Inverter> {
+    let name = text(record, columns, "Name")?;
+    let (brand, model) = name.split_once(':')?;
+    let ac_power = number(record, columns, "Paco").filter(|w| *w > 0.)?;
+    Some(Inverter {
+        brand: brand.trim().into(),
+        model: model.trim().into(),
+        max_pv_power: Power::from_w(number(record, columns, "Pdco").unwrap_or(ac_power)),
+        ac_power: Power::from_w(ac_power),
+        ..Default::default()
+    })
+}
+
+/// Reads a CSV file of any supported format into `library`
+pub fn import(library: &mut Library, data: impl Read) -> Result<ImportReport, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::Headers)
+        .from_reader(data);
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let source = Source::detect(&headers).ok_or("Unknown CSV format")?;
+    let columns: HashMap<String, usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.to_string(), i))
+        .collect();
+
+    let mut report = ImportReport {
+        source,
+        added: 0,
+        updated: 0,
+        skipped: 0,
+    };
+    let mut panels = index_of(&library.panels);
+    let mut inverters = index_of(&library.inverters);
+    let mut batteries = index_of(&library.batteries);
+    let mut cost_items = index_of(&library.cost_items);
+
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let item = match source {
+            Source::CecModules => cec_module(&record, &columns).map(Item::Panel),
+            Source::CecInverters => cec_inverter(&record, &columns).map(Item::Inverter),
+            Source::Template => record
+                .deserialize::<TemplateRow>(Some(&headers))
+                .ok()
+                .and_then(TemplateRow::item),
+        };
+        match item {
+            Some(Item::Panel(panel)) => {
+                upsert(&mut library.panels, &mut panels, panel, &mut report)
+            }
+            Some(Item::Inverter(inverter)) => upsert(
+                &mut library.inverters,
+                &mut inverters,
+                inverter,
+                &mut report,
+            ),
+            Some(Item::Battery(battery)) => {
+                upsert(&mut library.batteries, &mut batteries, battery, &mut report)
+            }
+            Some(Item::CostItem(item)) => {
+                upsert(&mut library.cost_items, &mut cost_items, item, &mut report)
+            }
+            None => report.skipped += 1,
+        }
+    }
+    Ok(report)
+}
+
+#[test]
+fn import_csv() {
+    use crate::components::Layer;
+
+    let mut library = Library::default();
+    let panels = library.panels.len();
+    library.panels[0].price_gross = true;
+    library.panels[0].vat_rate = 0.19;
+
+    let modules = "Name,Manufacturer,Technology,STC,PTC,A_c,Length,Width\n\
+        Units,,,W,W,m2,m,m\n\
+        [0],,,,,,,\n\
+        Aleo LEO Black 400 Wp Premium - L84S400,Aleo,Mono-c-Si,400.2,370,2.0,1.752,1.144\n\
+        Trina Solar TSM-430NEG9R.28,Trina Solar,Mono-c-Si,430.1,405,2.0,1.762,1.134\n\
+        Trina Solar TSM-430NEG9R.28,Trina Solar,Mono-c-Si,430.1,405,2.0,1.762,1.134\n";
+    let report = import(&mut library, modules.as_bytes()).unwrap();
+    assert_eq!(report.source, Source::CecModules);
+    assert_eq!((report.added, report.updated, report.skipped), (1, 2, 2));
+    assert_eq!(library.panels.len(), panels + 1);
+    // the price of the bundled panel survives
+    assert_eq!(library.panels[0].price.amount(), 206.);
+    assert!(library.panels[0].price_gross);
+    assert_eq!(library.panels[0].vat_rate, 0.19);
+    // and so does the built-in panel
+    assert_eq!(library.panels[0].layer, Layer::Personal);
+    assert_eq!(library.layer(Layer::BuiltIn).panels.len(), 1);
+    assert_eq!(library.panels[0].peak_power.w(), 400.2);
+    let trina = library.panels.last().unwrap();
+    assert_eq!(trina.model, "TSM-430NEG9R.28");
+    assert!((trina.area().m2() - 1.998).abs() < 0.001);
+
+    let inverters = "Name,Vac,Pso,Paco,Pdco,Vdco\n\
+        Units,V,W,W,W,V\n\
+        \"SMA America: SB5.0-1SP-US-40 [240V]\",240,30,5000,5150,380\n";
+    let report = import(&mut library, inverters.as_bytes()).unwrap();
+    assert_eq!((report.added, report.skipped), (1, 1));
+    let sma = library.inverters.last().unwrap();
+    assert_eq!((sma.brand.as_str(), sma.ac_power.kw()), ("SMA America", 5.));
+
+    let template = include_str!("../library_template.csv");
+    let report = import(&mut library, template.as_bytes()).unwrap();
+    assert_eq!(report.source, Source::Template);
+    assert_eq!(report.skipped, 0);
+    let battery = library.batteries.last().unwrap();
+    assert_eq!(battery.chemistry, Chemistry::Lfp);
+    let hook = library.cost_items.last().unwrap();
+    assert!(hook.price_gross);
+    assert_eq!(hook.vat_rate, 0.081);
+    assert_eq!(hook.currency, Currency("CHF".into()));
+    // gross prices need the VAT rate they include
+    let gross = "kind,brand,model,price,price_gross,vat_rate,power_w,width_cm,height_cm\n\
+        panel,A,C,99,yes,,400,100,170\n\
+        panel,A,D,99,maybe,19,400,100,170\n";
+    let report = import(&mut library, gross.as_bytes()).unwrap();
+    assert_eq!(report.skipped, 2);
+    // templates from before currencies
+    let old = "kind,brand,model,price_eur,power_w,width_cm,height_cm\npanel,A,B,99,400,100,170\n";
+    import(&mut library, old.as_bytes()).unwrap();
+    assert_eq!(library.panels.last().unwrap().price.amount(), 99.);
+
+    assert!(import(&mut library, "a,b\n1,2\n".as_bytes()).is_err());
+}
@@ -1,7 +1,29 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod analysis;
 mod app;
+pub mod catalogue;
+mod charts;
 pub use app::PVApp;
-mod components;
-mod translation;
-mod panel_orientation;
\ No newline at end of file
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+mod comparison;
+pub mod components;
+pub mod currency;
+pub mod format;
+pub mod html;
+pub mod import;
+mod library_browser;
+pub mod markdown;
+pub mod optimizer;
+mod orientation_map;
+mod panel_orientation;
+pub mod pdf;
+pub mod quotes;
+pub mod report;
+pub mod simulation;
+pub mod sizing;
+pub mod tax;
+pub mod timeseries;
+pub mod translation;
+pub mod units;
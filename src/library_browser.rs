@@ -0,0 +1,706 @@
+//! Library browser: a compact table of panels, inverters or batteries with text search,
+//! filters and sortable columns. Items are edited in a separate window.
+
+use egui::Widget;
+use egui_extras::{Column, TableBuilder};
+use egui_phosphor::regular::*;
+
+use crate::{
+    catalogue::{revert, Product},
+    components::{
+        Battery, Chemistry, CostItem, CostUnit, Inverter, Layer, Library, Panel, Project,
+    },
+    currency::Currency,
+    format,
+    quotes::{Date, Pricing, Quote},
+    tr,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Category {
+    #[default]
+    Panels,
+    Inverters,
+    Batteries,
+    CostItems,
+}
+
+impl Category {
+    pub const ALL: [Category; 4] = [
+        Category::Panels,
+        Category::Inverters,
+        Category::Batteries,
+        Category::CostItems,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            Category::Panels => format!("{RECTANGLE} {}", tr!("panels")),
+            Category::Inverters => format!("{LIGHTNING} {}", tr!("inverters")),
+            Category::Batteries => format!("{BATTERY_FULL} {}", tr!("batteries")),
+            Category::CostItems => format!("{WRENCH} {}", tr!("cost_items")),
+        }
+    }
+
+    /// Unit of the power column: peak power of panels, AC power of inverters and usable
+    /// capacity of battery modules. Cost items have none.
+    fn unit(&self) -> Option<&'static str> {
+        match self {
+            Category::Panels => Some("Wp"),
+            Category::Inverters => Some("kW"),
+            Category::Batteries => Some("kWh"),
+            Category::CostItems => None,
+        }
+    }
+
+    fn len(&self, library: &Library) -> usize {
+        match self {
+            Category::Panels => library.panels.len(),
+            Category::Inverters => library.inverters.len(),
+            Category::Batteries => library.batteries.len(),
+            Category::CostItems => library.cost_items.len(),
+        }
+    }
+
+    /// Removes item `index` from the library and from the projects and the battery chosen for
+    /// sizing, which refer to the items after it by a lower index from then on
+    pub fn remove(
+        &self,
+        index: usize,
+        library: &mut Library,
+        projects: &mut [Project],
+        sizing_battery: &mut Option<usize>,
+    ) {
+        if index >= self.len(library) {
+            return;
+        }
+        match self {
+            Category::Panels => _ = library.panels.remove(index),
+            Category::Inverters => _ = library.inverters.remove(index),
+            Category::Batteries => _ = library.batteries.remove(index),
+            Category::CostItems => _ = library.cost_items.remove(index),
+        }
+        if *self == Category::Batteries {
+            *sizing_battery = match *sizing_battery {
+                Some(id) if id == index => None,
+                Some(id) if id > index => Some(id - 1),
+                id => id,
+            };
+        }
+        for project in projects {
+            let ids = match self {
+                Category::Panels => &mut project.panels,
+                Category::Inverters => &mut project.inverters,
+                Category::Batteries => &mut project.batteries,
+                Category::CostItems => {
+                    project.cost_items.retain(|line| line.item != index);
+                    for line in project.cost_items.iter_mut().filter(|l| l.item > index) {
+                        line.item -= 1;
+                    }
+                    continue;
+                }
+            };
+            ids.retain(|id| *id != index);
+            for id in ids.iter_mut().filter(|id| **id > index) {
+                *id -= 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    #[default]
+    Brand,
+    Model,
+    Power,
+    Price,
+    /// Price per unit of the power column, e.g. €/Wp
+    UnitPrice,
+    Efficiency,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Filter {
+    /// Words that must all appear in brand or model
+    pub text: String,
+    /// In the unit of the power column, 0 for no limit
+    pub min_power: f32,
+    pub max_power: f32,
+    /// 0 for no limit
+    pub max_unit_price: f32,
+    /// 0-1
+    pub min_efficiency: f32,
+    pub chemistry: Option<Chemistry>,
+    pub phases: Option<u8>,
+    pub layer: Option<Layer>,
+}
+
+/// What the browser shows of an item
+struct Row<'a> {
+    index: usize,
+    brand: &'a str,
+    model: &'a str,
+    power: f32,
+    /// `None` without exchange rate
+    price: Option<f32>,
+    currency: &'a Currency,
+    /// NaN for inverters, whose efficiency isn't known
+    efficiency: f32,
+    chemistry: Option<Chemistry>,
+    phases: Option<u8>,
+    cost_unit: Option<CostUnit>,
+    layer: Layer,
+    /// Date of the quote the price is from
+    quote_date: Option<Date>,
+    stale: bool,
+}
+
+impl Row<'_> {
+    fn unit_price(&self) -> f32 {
+        match self.power {
+            power if power > 0. => self.price.map_or(f32::INFINITY, |price| price / power),
+            _ => f32::INFINITY,
+        }
+    }
+
+    fn key(&self, key: SortKey) -> f32 {
+        match key {
+            SortKey::Brand | SortKey::Model => 0.,
+            SortKey::Power => self.power,
+            SortKey::Price => self.price.unwrap_or(f32::INFINITY),
+            SortKey::UnitPrice => self.unit_price(),
+            SortKey::Efficiency => self.efficiency,
+        }
+    }
+}
+
+fn rows<'a>(
+    category: Category,
+    library: &'a Library,
+    pricing: &Pricing,
+    max_price_age_days: u32,
+) -> Vec<Row<'a>> {
+    let quote_date = |quotes: &[Quote]| pricing.quote(quotes).map(|q| q.date);
+    let stale = |quotes: &[Quote]| pricing.is_stale(quotes, max_price_age_days);
+    match category {
+        Category::Panels => library
+            .panels
+            .iter()
+            .enumerate()
+            .map(|(index, p)| Row {
+                index,
+                brand: &p.brand,
+                model: &p.model,
+                power: p.peak_power.w(),
+                price: p.price(pricing).map(|p| p.amount()),
+                currency: &p.currency,
+                efficiency: p.efficiency(),
+                chemistry: None,
+                phases: None,
+                cost_unit: None,
+                layer: p.layer,
+                quote_date: quote_date(&p.quotes),
+                stale: stale(&p.quotes),
+            })
+            .collect(),
+        Category::Inverters => library
+            .inverters
+            .iter()
+            .enumerate()
+            .map(|(index, i)| Row {
+                index,
+                brand: &i.brand,
+                model: &i.model,
+                power: i.ac_power.kw(),
+                price: i.price(pricing).map(|p| p.amount()),
+                currency: &i.currency,
+                efficiency: f32::NAN,
+                chemistry: None,
+                phases: Some(i.phases),
+                cost_unit: None,
+                layer: i.layer,
+                quote_date: quote_date(&i.quotes),
+                stale: stale(&i.quotes),
+            })
+            .collect(),
+        Category::Batteries => library
+            .batteries
+            .iter()
+            .enumerate()
+            .map(|(index, b)| Row {
+                index,
+                brand: &b.brand,
+                model: &b.model,
+                power: b.usable.kwh(),
+                price: b.price(pricing).map(|p| p.amount()),
+                currency: &b.currency,
+                efficiency: b.efficiency,
+                chemistry: Some(b.chemistry),
+                phases: None,
+                cost_unit: None,
+                layer: b.layer,
+                quote_date: quote_date(&b.quotes),
+                stale: stale(&b.quotes),
+            })
+            .collect(),
+        Category::CostItems => library
+            .cost_items
+            .iter()
+            .enumerate()
+            .map(|(index, c)| Row {
+                index,
+                brand: &c.brand,
+                model: &c.model,
+                power: f32::NAN,
+                price: c.price(pricing).map(|p| p.amount()),
+                currency: &c.currency,
+                efficiency: f32::NAN,
+                chemistry: None,
+                phases: None,
+                cost_unit: Some(c.unit),
+                layer: c.layer,
+                quote_date: quote_date(&c.quotes),
+                stale: stale(&c.quotes),
+            })
+            .collect(),
+    }
+}
+
+impl Filter {
+    fn matches(&self, row: &Row<'_>) -> bool {
+        let name = format!("{} {}", row.brand, row.model).to_lowercase();
+        self.text
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| name.contains(word))
+            && (self.min_power <= 0. || row.power >= self.min_power)
+            && (self.max_power <= 0. || row.power <= self.max_power)
+            && (self.max_unit_price <= 0. || row.unit_price() <= self.max_unit_price)
+            && (self.min_efficiency <= 0.
+                || row.efficiency.is_nan()
+                || row.efficiency >= self.min_efficiency)
+            && (self.chemistry.is_none() || row.chemistry == self.chemistry)
+            && (self.phases.is_none() || row.phases == self.phases)
+            && (self.layer.is_none() || Some(row.layer) == self.layer)
+    }
+}
+
+fn layer_icon(layer: Layer) -> &'static str {
+    match layer {
+        Layer::BuiltIn => PACKAGE,
+        Layer::Company => BUILDINGS,
+        Layer::Personal => USER,
+    }
+}
+
+/// Shows the edit grid of `item`. Changing an item of another layer makes it a personal
+/// override, which can be reverted to show the item of the lower layer again.
+fn edit<T>(ui: &mut egui::Ui, item: &mut T)
+where
+    T: Product + Clone + PartialEq,
+    for<'a> &'a mut T: Widget,
+{
+    let layer = *item.layer();
+    let below = item.overridden().as_mut().map(|below| *below.layer());
+    let mut reverted = false;
+    ui.horizontal(|ui| {
+        ui.label(format!("{} {}", layer_icon(layer), layer.label()));
+        if let Some(below) = below {
+            reverted = ui
+                .small_button(format!(
+                    "{ARROW_COUNTER_CLOCKWISE} {}",
+                    tr!("revert_override", layer = below.label())
+                ))
+                .clicked();
+        }
+    });
+    if reverted {
+        revert(item);
+        return;
+    }
+    let before = item.clone();
+    ui.add(&mut *item);
+    if *item != before && layer != Layer::Personal {
+        *item.layer() = Layer::Personal;
+        *item.overridden() = Some(Box::new(before));
+    }
+}
+
+/// What the user asked for in the browser
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Add the item to the project
+    Add(Category, usize),
+    Delete(Category, usize),
+}
+
+#[derive(Debug, Default)]
+pub struct LibraryBrowser {
+    pub category: Category,
+    /// Prices shown, those of the active project
+    pub pricing: Pricing,
+    /// Quotes older than this are flagged
+    pub max_price_age_days: u32,
+    pub filter: Filter,
+    pub sort: SortKey,
+    pub descending: bool,
+    /// Item shown in the edit window
+    editing: Option<(Category, usize)>,
+}
+
+impl LibraryBrowser {
+    /// Indices of the items of the current category that pass the filter, in sort order
+    pub fn visible(&self, library: &Library) -> Vec<usize> {
+        let mut rows = rows(
+            self.category,
+            library,
+            &self.pricing,
+            self.max_price_age_days,
+        )
+        .into_iter()
+        .filter(|row| self.filter.matches(row))
+        .collect::<Vec<_>>();
+        rows.sort_by(|a, b| {
+            let order = match self.sort {
+                SortKey::Brand => a
+                    .brand
+                    .to_lowercase()
+                    .cmp(&b.brand.to_lowercase())
+                    .then_with(|| a.model.to_lowercase().cmp(&b.model.to_lowercase())),
+                SortKey::Model => a.model.to_lowercase().cmp(&b.model.to_lowercase()),
+                key => a.key(key).total_cmp(&b.key(key)),
+            };
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        rows.into_iter().map(|row| row.index).collect()
+    }
+
+    fn filter_ui(&mut self, ui: &mut egui::Ui) {
+        let unit = self.category.unit();
+        let symbol = self.pricing.currency.symbol();
+        let filter = &mut self.filter;
+        egui::Grid::new("library_filter")
+            .num_columns(2)
+            .show(ui, |ui| {
+                if let Some(unit) = unit {
+                    ui.label(tr!("power_range", unit = unit));
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut filter.min_power).clamp_range(0..=100_000),
+                        );
+                        ui.label("–");
+                        ui.add(
+                            egui::DragValue::new(&mut filter.max_power).clamp_range(0..=100_000),
+                        );
+                    });
+                    ui.end_row();
+                    ui.label(tr!("max_unit_price", unit = unit));
+                    ui.add(
+                        egui::DragValue::new(&mut filter.max_unit_price)
+                            .clamp_range(0..=100_000)
+                            .speed(0.01)
+                            .suffix(format!(" {symbol}")),
+                    );
+                    ui.end_row();
+                }
+                match self.category {
+                    Category::Panels | Category::Batteries => {
+                        ui.label(tr!("min_efficiency"));
+                        ui.add(
+                            egui::DragValue::new(&mut filter.min_efficiency)
+                                .clamp_range(0..=1)
+                                .speed(0.005)
+                                .custom_formatter(|v, _| format::percent(v as f32)),
+                        );
+                        ui.end_row();
+                    }
+                    Category::Inverters | Category::CostItems => {}
+                }
+                match self.category {
+                    Category::Inverters => {
+                        ui.label(tr!("phases"));
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut filter.phases, None, tr!("all"));
+                            ui.selectable_value(&mut filter.phases, Some(1), "1");
+                            ui.selectable_value(&mut filter.phases, Some(3), "3");
+                        });
+                        ui.end_row();
+                    }
+                    Category::Batteries => {
+                        ui.label(tr!("chemistry"));
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut filter.chemistry, None, tr!("all"));
+                            for chemistry in Chemistry::ALL {
+                                ui.selectable_value(
+                                    &mut filter.chemistry,
+                                    Some(chemistry),
+                                    chemistry.label(),
+                                );
+                            }
+                        });
+                        ui.end_row();
+                    }
+                    Category::Panels | Category::CostItems => {}
+                }
+                ui.label(tr!("layer"));
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut filter.layer, None, tr!("all"));
+                    for layer in Layer::ALL {
+                        ui.selectable_value(&mut filter.layer, Some(layer), layer.label());
+                    }
+                });
+                ui.end_row();
+            });
+        if ui.button(tr!("reset_filter")).clicked() {
+            self.filter = Filter {
+                text: std::mem::take(&mut self.filter.text),
+                ..Default::default()
+            };
+        }
+    }
+
+    /// A header that sorts by `key` when clicked, and reverses the order on a second click
+    fn sort_header(&mut self, ui: &mut egui::Ui, key: SortKey, label: String) {
+        let arrow = match (self.sort == key, self.descending) {
+            (true, false) => CARET_UP,
+            (true, true) => CARET_DOWN,
+            (false, _) => "",
+        };
+        if ui
+            .add(
+                egui::Label::new(egui::RichText::new(format!("{label} {arrow}")).strong())
+                    .sense(egui::Sense::click()),
+            )
+            .clicked()
+        {
+            if self.sort == key {
+                self.descending = !self.descending;
+            } else {
+                (self.sort, self.descending) = (key, false);
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, library: &mut Library) -> Option<Action> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            for category in Category::ALL {
+                ui.selectable_value(&mut self.category, category, category.label());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(MAGNIFYING_GLASS);
+            ui.add(egui::TextEdit::singleline(&mut self.filter.text).hint_text(tr!("search")));
+        });
+        ui.collapsing(format!("{FUNNEL} {}", tr!("filter")), |ui| {
+            self.filter_ui(ui)
+        });
+
+        let visible = self.visible(library);
+        let category = self.category;
+        ui.horizontal(|ui| {
+            ui.label(tr!(
+                "items_shown",
+                shown = visible.len(),
+                total = category.len(library)
+            ));
+            if ui.button(format!("{PLUS} {}", tr!("new"))).clicked() {
+                let index = category.len(library);
+                match category {
+                    Category::Panels => library.panels.push(Panel::default()),
+                    Category::Inverters => library.inverters.push(Inverter::default()),
+                    Category::Batteries => library.batteries.push(Battery::default()),
+                    Category::CostItems => library.cost_items.push(CostItem::default()),
+                }
+                self.editing = Some((category, index));
+            }
+        });
+
+        let unit = category.unit();
+        let row_height = ui.spacing().interact_size.y;
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .column(Column::auto())
+            .column(Column::initial(70.).clip(true))
+            .column(Column::initial(120.).clip(true))
+            .columns(Column::auto(), 5)
+            .column(Column::remainder())
+            .header(row_height, |mut header| {
+                header.col(|_| {});
+                header.col(|ui| self.sort_header(ui, SortKey::Brand, tr!("brand")));
+                header.col(|ui| self.sort_header(ui, SortKey::Model, tr!("model")));
+                header.col(|ui| {
+                    if let Some(unit) = unit {
+                        self.sort_header(ui, SortKey::Power, unit.into());
+                    }
+                });
+                header.col(|ui| self.sort_header(ui, SortKey::Price, tr!("price")));
+                header.col(|ui| {
+                    if let Some(unit) = unit {
+                        self.sort_header(
+                            ui,
+                            SortKey::UnitPrice,
+                            format!("{}/{unit}", self.pricing.currency.symbol()),
+                        );
+                    }
+                });
+                header.col(|ui| {
+                    if matches!(category, Category::Panels | Category::Batteries) {
+                        self.sort_header(ui, SortKey::Efficiency, tr!("efficiency"));
+                    }
+                });
+                header.col(|_| {});
+                header.col(|_| {});
+            })
+            .body(|body| {
+                let rows = rows(category, library, &self.pricing, self.max_price_age_days);
+                body.rows(row_height, visible.len(), |mut table_row| {
+                    let row = &rows[visible[table_row.index()]];
+                    table_row.col(|ui| {
+                        ui.label(layer_icon(row.layer))
+                            .on_hover_text(row.layer.label());
+                    });
+                    table_row.col(|ui| _ = ui.label(row.brand));
+                    table_row.col(|ui| _ = ui.label(row.model).on_hover_text(row.model));
+                    table_row.col(|ui| {
+                        ui.label(format::number(
+                            row.power,
+                            if category == Category::Panels { 0 } else { 1 },
+                        ));
+                    });
+                    table_row.col(|ui| {
+                        let Some(price) = row.price else {
+                            ui.label("?").on_hover_text(tr!(
+                                "missing_rates",
+                                currencies = row.currency,
+                                currency = self.pricing.currency
+                            ));
+                            return;
+                        };
+                        let price = ui.label(format::price(price, &self.pricing.currency));
+                        if row.stale {
+                            ui.label(WARNING).on_hover_text(tr!(
+                                "price_outdated",
+                                days = self.max_price_age_days
+                            ));
+                        } else if let Some(date) = row.quote_date {
+                            price.on_hover_text(tr!("quoted_on", date = date.to_string()));
+                        }
+                    });
+                    table_row.col(|ui| _ = ui.label(format::number(row.unit_price(), 2)));
+                    table_row.col(|ui| {
+                        ui.label(match (row.chemistry, row.phases) {
+                            _ if row.cost_unit.is_some() => {
+                                row.cost_unit.map(|u| u.label()).unwrap_or_default()
+                            }
+                            (Some(chemistry), _) => {
+                                format!("{} {}", format::percent(row.efficiency), chemistry.label())
+                            }
+                            (_, Some(0)) => "?".into(),
+                            (_, Some(phases)) => tr!("phases_value", phases = phases),
+                            _ => format::percent(row.efficiency),
+                        });
+                    });
+                    table_row.col(|ui| {
+                        if ui.small_button(PLUS).on_hover_text(tr!("add")).clicked() {
+                            action = Some(Action::Add(category, row.index));
+                        }
+                    });
+                    table_row.col(|ui| {
+                        if ui.small_button(PENCIL_SIMPLE).clicked() {
+                            self.editing = Some((category, row.index));
+                        }
+                        if ui.small_button(TRASH_SIMPLE).clicked() {
+                            action = Some(Action::Delete(category, row.index));
+                        }
+                    });
+                });
+            });
+
+        if let Some(Action::Delete(..)) = action {
+            self.editing = None;
+        }
+        self.edit_window(ui.ctx(), library);
+        action
+    }
+
+    /// The full edit grid of the item being edited
+    fn edit_window(&mut self, ctx: &egui::Context, library: &mut Library) {
+        let Some((category, index)) = self.editing else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new(format!("{PENCIL_SIMPLE} {}", tr!("edit")))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| match category {
+                Category::Panels => library.panels.get_mut(index).map(|p| edit(ui, p)),
+                Category::Inverters => library.inverters.get_mut(index).map(|i| edit(ui, i)),
+                Category::Batteries => library.batteries.get_mut(index).map(|b| edit(ui, b)),
+                Category::CostItems => library.cost_items.get_mut(index).map(|c| edit(ui, c)),
+            });
+        if !open || index >= category.len(library) {
+            self.editing = None;
+        }
+    }
+}
+
+#[test]
+fn browser() {
+    let library = Library::default();
+    let mut browser = LibraryBrowser {
+        category: Category::Inverters,
+        sort: SortKey::Power,
+        ..Default::default()
+    };
+    // Kostal 4.2 kW before Fronius 6 kW
+    assert_eq!(browser.visible(&library), vec![1, 0]);
+    browser.descending = true;
+    assert_eq!(browser.visible(&library), vec![0, 1]);
+    browser.filter.text = "plenti KOSTAL".into();
+    assert_eq!(browser.visible(&library), vec![1]);
+    browser.filter = Filter {
+        min_power: 5.,
+        ..Default::default()
+    };
+    assert_eq!(browser.visible(&library), vec![0]);
+
+    browser.category = Category::Batteries;
+    browser.filter = Filter {
+        chemistry: Some(Chemistry::Lfp),
+        ..Default::default()
+    };
+    assert_eq!(browser.visible(&library), vec![1]);
+
+    browser.category = Category::Panels;
+    browser.filter = Filter {
+        max_unit_price: 0.6,
+        min_efficiency: 0.19,
+        ..Default::default()
+    };
+    // 206 € for 400 Wp on 2 m²
+    assert_eq!(browser.visible(&library), vec![0]);
+    browser.filter.min_efficiency = 0.2;
+    assert!(browser.visible(&library).is_empty());
+
+    let mut library = library;
+    let mut projects = vec![Project {
+        inverters: vec![0, 1, 1],
+        ..Default::default()
+    }];
+    let mut sizing_battery = Some(1);
+    Category::Inverters.remove(0, &mut library, &mut projects, &mut sizing_battery);
+    assert_eq!(library.inverters.len(), 1);
+    assert_eq!(projects[0].inverters, vec![0, 0]);
+    assert_eq!(sizing_battery, Some(1));
+    Category::Batteries.remove(0, &mut library, &mut projects, &mut sizing_battery);
+    assert_eq!(sizing_battery, Some(0));
+    Category::Batteries.remove(0, &mut library, &mut projects, &mut sizing_battery);
+    assert_eq!(sizing_battery, None);
+}
@@ -6,6 +6,15 @@
 fn main() -> eframe::Result<()> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    // Batch calculations without a window, see `pvcalc --cli --help`
+    if std::env::args().any(|arg| arg == "--cli") {
+        if let Err(e) = eframe_template::cli::run(std::env::args().skip(1)) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 300.0])
@@ -0,0 +1,116 @@
+//! Renders a [`Report`] as GitHub flavoured Markdown, e.g. to paste into a ticket. Charts
+//! become tables.
+
+use std::fmt::Write;
+
+use crate::{
+    format,
+    report::{Block, Chart, Report, Table},
+};
+
+/// Keeps `text` on one line and from breaking tables or emphasis
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('*', "\\*")
+        .replace('_', "\\_")
+        .replace('\n', ", ")
+}
+
+fn table(out: &mut String, table: &Table) {
+    let columns = table.header.len();
+    let row = |out: &mut String, cells: &[String], bold: bool| {
+        out.push('|');
+        for text in cells.iter().map(|c| escape(c)) {
+            if bold && !text.is_empty() {
+                _ = write!(out, " **{text}** |");
+            } else {
+                _ = write!(out, " {text} |");
+            }
+        }
+        out.push('\n');
+    };
+    row(out, &table.header, false);
+    out.push('|');
+    for c in 0..columns {
+        out.push_str(if c == 0 { " --- |" } else { " ---: |" });
+    }
+    out.push('\n');
+    for cells in &table.rows {
+        row(out, cells, false);
+    }
+    for cells in &table.totals {
+        row(out, cells, true);
+    }
+    out.push('\n');
+}
+
+fn chart(out: &mut String, chart: &Chart) {
+    _ = writeln!(
+        out,
+        "**{} ({})**\n",
+        escape(&chart.title),
+        escape(&chart.unit)
+    );
+    table(
+        out,
+        &Table {
+            header: std::iter::once(String::new())
+                .chain(chart.series.iter().map(|s| s.name.clone()))
+                .collect(),
+            rows: chart
+                .labels
+                .iter()
+                .enumerate()
+                .map(|(i, label)| {
+                    std::iter::once(label.clone())
+                        .chain(chart.series.iter().map(|s| {
+                            s.values
+                                .get(i)
+                                .map_or(String::new(), |v| format::number(*v, 0))
+                        }))
+                        .collect()
+                })
+                .collect(),
+            totals: vec![],
+        },
+    );
+}
+
+/// The report as Markdown text
+pub fn render(report: &Report) -> String {
+    let mut out = format!(
+        "# {}\n\n{} – {}\n\n",
+        escape(&report.title),
+        escape(&report.subtitle),
+        report.date
+    );
+    for section in &report.sections {
+        _ = writeln!(out, "## {}\n", escape(&section.heading));
+        for block in &section.blocks {
+            match block {
+                Block::Text(text) => _ = writeln!(out, "{}\n", escape(text)),
+                Block::Facts(facts) => {
+                    for (label, value) in facts {
+                        _ = writeln!(out, "- **{}:** {}", escape(label), escape(value));
+                    }
+                    out.push('\n');
+                }
+                Block::Table(t) => table(&mut out, t),
+                Block::Chart(c) => chart(&mut out, c),
+            }
+        }
+    }
+    _ = writeln!(out, "*{}*", escape(&report.disclaimer));
+    out
+}
+
+#[test]
+fn markdown() {
+    let markdown = render(&crate::report::sample());
+    assert!(markdown.starts_with("# "));
+    assert!(markdown.contains("Heidestraße 17, 51147 Köln"));
+    // the monthly charts as tables with a row per month
+    assert!(markdown.lines().filter(|l| l.starts_with("| Jan")).count() >= 2);
+    assert!(markdown.lines().any(|l| l.starts_with("| --- | ---: |")));
+}
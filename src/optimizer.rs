@@ -0,0 +1,230 @@
+//! Array size optimizer: tries every panel count that fits the roof and the budget with the
+//! cheapest inverter of the library that takes it, and picks the best configuration. Only
+//! configurations within the constraints are simulated.
+
+use crate::{
+    analysis::{analyze, Investment},
+    catalogue::Product,
+    components::{Library, Project},
+    tr,
+    units::Money,
+};
+
+/// Panel counts tried if the roof area is unknown
+const MAX_PANELS_WITHOUT_ROOF: usize = 40;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Objective {
+    #[default]
+    MaxNpv,
+    MinPayback,
+}
+
+impl Objective {
+    pub const ALL: [Objective; 2] = [Objective::MaxNpv, Objective::MinPayback];
+
+    pub fn label(&self) -> String {
+        match self {
+            Objective::MaxNpv => tr!("objective_npv"),
+            Objective::MinPayback => tr!("objective_payback"),
+        }
+    }
+}
+
+/// Limits for the optimizer. The feed-in limit is taken from the project.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Constraints {
+    /// Maximum total price, 0 for no limit
+    pub budget: Money,
+}
+
+/// One configuration that was simulated
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Candidate {
+    pub panels: usize,
+    /// Library inverter index
+    pub inverter: Option<usize>,
+    pub kwp: f32,
+    pub price: f32,
+    /// 0-1, share of the consumption covered by PV
+    pub autarky: f32,
+    pub npv: f32,
+    pub payback_years: f32,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Optimization {
+    /// Library panel index used for all configurations
+    pub panel: usize,
+    /// The configurations within the constraints, one per panel count
+    pub candidates: Vec<Candidate>,
+    /// Index of the best candidate for the objective
+    pub best: Option<usize>,
+    /// Indices of the candidates no other one beats in both price and autarky, cheapest first
+    pub pareto: Vec<usize>,
+}
+
+impl Optimization {
+    pub fn best(&self) -> Option<&Candidate> {
+        self.best.and_then(|i| self.candidates.get(i))
+    }
+}
+
+/// How many panels of the given type fit the roof of `project`
+pub fn max_panels(project: &Project, library: &Library, panel: usize) -> usize {
+    let panel_area = library.panels.get(panel).map(|p| p.area().m2());
+    match panel_area {
+        Some(area) if area > 0. && project.roof_area.m2() > 0. => {
+            (project.roof_area.m2() / area).floor() as usize
+        }
+        _ => MAX_PANELS_WITHOUT_ROOF,
+    }
+}
+
+pub fn optimize(
+    project: &Project,
+    library: &Library,
+    objective: Objective,
+    constraints: Constraints,
+) -> Optimization {
+    // the panel type already chosen, or the first one of the library
+    let panel = project.panels.first().copied().unwrap_or_default();
+    let pricing = project.pricing(library);
+    let budget = constraints.budget.amount();
+
+    let mut candidates = vec![];
+    for panels in 1..=max_panels(project, library, panel) {
+        let mut configuration = Project {
+            panels: vec![panel; panels],
+            inverters: vec![],
+            ..project.clone()
+        };
+        let kwp = configuration.sum(library).peak_power.kw();
+        // the simulation doesn't depend on the inverter, so the cheapest one taking the array
+        // beats all others
+        let inverter = if library.inverters.is_empty() {
+            None
+        } else {
+            let cheapest = (library.inverters.iter().enumerate())
+                .filter(|(_, i)| kwp <= i.max_pv_power.kw())
+                .filter_map(|(id, i)| Some((id, i.price(&pricing)?)))
+                .min_by(|(_, a), (_, b)| a.amount().total_cmp(&b.amount()));
+            match cheapest {
+                Some((id, _)) => Some(id),
+                // nor does one take more panels
+                None => break,
+            }
+        };
+        configuration.inverters = inverter.into_iter().collect();
+        // more panels only cost more
+        if budget > 0. && Investment::new(&configuration, library).paid > budget {
+            break;
+        }
+        let analysis = analyze(&configuration, library);
+        candidates.push(Candidate {
+            panels,
+            inverter,
+            kwp: analysis.kwp,
+            price: analysis.price_sum,
+            autarky: analysis.autarky,
+            npv: analysis.npv,
+            payback_years: analysis.payback_years,
+        });
+    }
+
+    let best = candidates
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| match objective {
+            Objective::MaxNpv => a.npv.total_cmp(&b.npv),
+            Objective::MinPayback => b.payback_years.total_cmp(&a.payback_years),
+        })
+        .map(|(i, _)| i);
+
+    Optimization {
+        panel,
+        pareto: pareto_front(&candidates),
+        best,
+        candidates,
+    }
+}
+
+/// Indices of the candidates that are not both more expensive and less self-sufficient than
+/// another one, cheapest first
+pub fn pareto_front(candidates: &[Candidate]) -> Vec<usize> {
+    let mut order = (0..candidates.len()).collect::<Vec<_>>();
+    // for the same price, the more self-sufficient one first
+    order.sort_by(|a, b| {
+        let (a, b) = (&candidates[*a], &candidates[*b]);
+        a.price
+            .total_cmp(&b.price)
+            .then(b.autarky.total_cmp(&a.autarky))
+    });
+    let mut front = vec![];
+    let mut best_autarky = f32::NEG_INFINITY;
+    for i in order {
+        if candidates[i].autarky > best_autarky {
+            best_autarky = candidates[i].autarky;
+            front.push(i);
+        }
+    }
+    front
+}
+
+impl Candidate {
+    /// Replaces panels and inverters of `project` with this configuration
+    pub fn apply(&self, project: &mut Project, panel: usize) {
+        project.panels = vec![panel; self.panels];
+        project.inverters = self.inverter.into_iter().collect();
+    }
+}
+
+#[test]
+fn optimizer() {
+    let candidate = |price, autarky| Candidate {
+        price,
+        autarky,
+        ..Default::default()
+    };
+    let candidates = [
+        candidate(1000., 0.2),
+        candidate(2000., 0.1),
+        candidate(2000., 0.4),
+        candidate(3000., 0.4),
+        candidate(4000., 0.5),
+    ];
+    assert_eq!(pareto_front(&candidates), vec![0, 2, 4]);
+
+    let library = Library::default();
+    let project = Project {
+        panels: vec![0],
+        panel_angle_deg: 35.,
+        // room for 12 panels of 2 m²
+        roof_area: crate::units::Area::from_m2(25.),
+        ..Default::default()
+    };
+    assert_eq!(max_panels(&project, &library, 0), 12);
+    let budget = Money::from_amount(9000.);
+    let result = optimize(
+        &project,
+        &library,
+        Objective::MaxNpv,
+        Constraints { budget },
+    );
+    assert!(!result.candidates.is_empty());
+    assert!(result.candidates.iter().all(|c| c.panels <= 12));
+    assert!(result.candidates.iter().all(|c| c.price <= 9000.));
+    assert!(result.candidates.len() <= 12);
+    assert!(result.candidates.iter().all(|c| {
+        let inverter = &library.inverters[c.inverter.unwrap()];
+        c.kwp <= inverter.max_pv_power.kw()
+    }));
+    let best = result.best().unwrap();
+    assert!(result.candidates.iter().all(|c| c.npv <= best.npv));
+    assert!(!result.pareto.is_empty());
+
+    let mut applied = project.clone();
+    best.apply(&mut applied, result.panel);
+    assert_eq!(applied.panels.len(), best.panels);
+    assert_eq!(applied.inverters.len(), 1);
+}
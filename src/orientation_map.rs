@@ -0,0 +1,228 @@
+//! Input widgets for the panel orientation: a compass, and a heat map of the relative yield
+//! over orientation and tilt where clicking a cell picks it. The yield comes from the caller,
+//! which uses the fixed reference table of [`crate::panel_orientation::efficiency`].
+
+use egui::{Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Vec2, Widget};
+
+use crate::{
+    format,
+    panel_orientation::{from_equator, to_azimuth},
+    tr,
+};
+
+/// Cell size of the map in degrees
+const STEP_DEG: f32 = 5.;
+/// The map spans from facing the pole over east, the equator and west to the pole again
+const MAX_DEVIATION_DEG: f32 = 180.;
+const MAX_TILT_DEG: f32 = 90.;
+/// Space for the axis labels
+const MARGIN: Vec2 = Vec2::new(36., 20.);
+
+pub struct OrientationMap<'a> {
+    /// Compass azimuth, 0-360°
+    pub azimuth_deg: &'a mut f32,
+    /// 0-90°
+    pub tilt_deg: &'a mut f32,
+    /// The map is centered on facing the equator, which depends on the hemisphere
+    pub latitude_deg: f32,
+    /// Relative yield 0-1 for a deviation from facing the equator (-180..180°) and a tilt
+    pub efficiency: &'a dyn Fn(f32, f32) -> f32,
+}
+
+/// Compass letter of the main directions, for azimuths that are multiples of 90°
+fn direction(azimuth_deg: i32) -> String {
+    match azimuth_deg.rem_euclid(360) {
+        0 => tr!("compass_n"),
+        90 => tr!("compass_e"),
+        180 => tr!("compass_s"),
+        270 => tr!("compass_w"),
+        _ => String::new(),
+    }
+}
+
+/// From dark blue for poor over green to yellow for the best yield
+fn color(value: f32) -> Color32 {
+    let stops = [
+        (0.3, Color32::from_rgb(40, 60, 140)),
+        (0.65, Color32::from_rgb(60, 160, 120)),
+        (0.85, Color32::from_rgb(180, 200, 60)),
+        (1.0, Color32::from_rgb(250, 210, 40)),
+    ];
+    let value = value.clamp(stops[0].0, 1.);
+    for pair in stops.windows(2) {
+        let ((v0, c0), (v1, c1)) = (pair[0], pair[1]);
+        if value <= v1 {
+            let t = (value - v0) / (v1 - v0);
+            let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+            return Color32::from_rgb(
+                mix(c0.r(), c1.r()),
+                mix(c0.g(), c1.g()),
+                mix(c0.b(), c1.b()),
+            );
+        }
+    }
+    stops[3].1
+}
+
+impl Widget for OrientationMap<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let width = ui.available_width().min(540.);
+        let (rect, mut response) =
+            ui.allocate_exact_size(Vec2::new(width, width * 0.5), Sense::click());
+        let map = Rect::from_min_max(
+            rect.min + Vec2::new(MARGIN.x, 0.),
+            rect.max - Vec2::new(0., MARGIN.y),
+        );
+        let columns = (2. * MAX_DEVIATION_DEG / STEP_DEG) as usize + 1;
+        let rows = (MAX_TILT_DEG / STEP_DEG) as usize + 1;
+        let cell = Vec2::new(map.width() / columns as f32, map.height() / rows as f32);
+
+        // tilt 0° at the bottom
+        let cell_rect = |column: usize, row: usize| {
+            Rect::from_min_size(
+                Pos2::new(
+                    map.left() + column as f32 * cell.x,
+                    map.bottom() - (row + 1) as f32 * cell.y,
+                ),
+                cell,
+            )
+        };
+        let angles_at = |pos: Pos2| {
+            let column = ((pos.x - map.left()) / cell.x)
+                .floor()
+                .clamp(0., columns as f32 - 1.);
+            let row = ((map.bottom() - pos.y) / cell.y)
+                .floor()
+                .clamp(0., rows as f32 - 1.);
+            (column * STEP_DEG - MAX_DEVIATION_DEG, row * STEP_DEG)
+        };
+
+        let painter = ui.painter_at(rect);
+        for column in 0..columns {
+            for row in 0..rows {
+                let value = (self.efficiency)(
+                    column as f32 * STEP_DEG - MAX_DEVIATION_DEG,
+                    row as f32 * STEP_DEG,
+                );
+                painter.rect_filled(cell_rect(column, row), 0., color(value));
+            }
+        }
+
+        let font = FontId::proportional(10.);
+        let text_color = ui.visuals().text_color();
+        for deviation in (-180..=180).step_by(45) {
+            let x = map.left() + ((deviation as f32 + MAX_DEVIATION_DEG) / STEP_DEG + 0.5) * cell.x;
+            let azimuth = to_azimuth(deviation as f32, self.latitude_deg).round() as i32;
+            let label = match direction(azimuth) {
+                letter if letter.is_empty() => format!("{}°", azimuth.rem_euclid(360)),
+                letter => letter,
+            };
+            painter.text(
+                Pos2::new(x, map.bottom() + 2.),
+                Align2::CENTER_TOP,
+                label,
+                font.clone(),
+                text_color,
+            );
+        }
+        for tilt in (0..=90).step_by(15) {
+            let y = map.bottom() - (tilt as f32 / STEP_DEG + 0.5) * cell.y;
+            painter.text(
+                Pos2::new(map.left() - 4., y),
+                Align2::RIGHT_CENTER,
+                format!("{tilt}°"),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        // the current setting
+        let deviation = from_equator(*self.azimuth_deg, self.latitude_deg);
+        let current = Pos2::new(
+            map.left() + ((deviation + MAX_DEVIATION_DEG) / STEP_DEG + 0.5) * cell.x,
+            map.bottom() - (self.tilt_deg.clamp(0., MAX_TILT_DEG) / STEP_DEG + 0.5) * cell.y,
+        );
+        painter.circle_stroke(
+            current,
+            cell.min_elem().max(6.),
+            Stroke::new(2., Color32::WHITE),
+        );
+        painter.circle_stroke(
+            current,
+            cell.min_elem().max(6.) + 2.,
+            Stroke::new(1., Color32::BLACK),
+        );
+
+        if let Some(pos) = response.interact_pointer_pos() {
+            if response.clicked() && map.contains(pos) {
+                let (deviation, tilt) = angles_at(pos);
+                *self.azimuth_deg = to_azimuth(deviation, self.latitude_deg);
+                *self.tilt_deg = tilt;
+                response.mark_changed();
+            }
+        }
+        if let Some(pos) = response.hover_pos().filter(|p| map.contains(*p)) {
+            let (deviation, tilt) = angles_at(pos);
+            response = response.on_hover_text(tr!(
+                "orientation_map_hover",
+                azimuth = format::number(to_azimuth(deviation, self.latitude_deg), 0),
+                tilt = format::number(tilt, 0),
+                percent = format::percent((self.efficiency)(deviation, tilt))
+            ));
+        }
+        response
+    }
+}
+
+/// A compass rose to pick the direction the panels face. North is up.
+pub struct Compass<'a> {
+    pub azimuth_deg: &'a mut f32,
+}
+
+impl Widget for Compass<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let size = 3. * ui.spacing().interact_size.y;
+        let (rect, mut response) =
+            ui.allocate_exact_size(Vec2::splat(size), Sense::click_and_drag());
+        let center = rect.center();
+        let radius = size / 2. - 1.;
+        // clockwise from north, with the screen's y axis pointing down
+        let towards = |azimuth_deg: f32, length: f32| {
+            let angle = azimuth_deg.to_radians();
+            center + length * Vec2::new(angle.sin(), -angle.cos())
+        };
+
+        if let Some(pos) = response.interact_pointer_pos() {
+            let offset = pos - center;
+            if offset.length() > 2. {
+                *self.azimuth_deg = offset
+                    .x
+                    .atan2(-offset.y)
+                    .to_degrees()
+                    .rem_euclid(360.)
+                    .round();
+                response.mark_changed();
+            }
+        }
+
+        let visuals = ui.style().interact(&response);
+        let painter = ui.painter_at(rect);
+        painter.circle(center, radius, visuals.bg_fill, visuals.fg_stroke);
+        let font = FontId::proportional(9.);
+        for azimuth in (0..360).step_by(90) {
+            painter.text(
+                towards(azimuth as f32, radius - 6.),
+                Align2::CENTER_CENTER,
+                direction(azimuth),
+                font.clone(),
+                visuals.text_color(),
+            );
+        }
+        painter.line_segment(
+            [center, towards(*self.azimuth_deg, radius - 10.)],
+            Stroke::new(2., Color32::from_rgb(240, 180, 40)),
+        );
+        painter.circle_filled(center, 2., visuals.fg_stroke.color);
+        response
+    }
+}
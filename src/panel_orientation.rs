@@ -1,7 +1,45 @@
-use log::{info, debug};
+use log::debug;
 
-/// returns efficiency from 0-1 based on orientation deviation from South and inclination 
-pub fn efficiency(south_deviation_deg: f32, inclination_deg: f32) -> f32{
+/// Compass direction facing the equator: south in the northern hemisphere, north in the southern
+pub fn equator_azimuth(latitude_deg: f32) -> f32 {
+    if crate::simulation::southern_hemisphere(latitude_deg) {
+        0.
+    } else {
+        180.
+    }
+}
+
+/// Signed deviation of a compass azimuth from facing the equator, -180..180°.
+/// Negative is towards east, positive towards west, in both hemispheres.
+pub fn from_equator(azimuth_deg: f32, latitude_deg: f32) -> f32 {
+    let deviation = (azimuth_deg - equator_azimuth(latitude_deg)).rem_euclid(360.);
+    let deviation = if deviation > 180. {
+        deviation - 360.
+    } else {
+        deviation
+    };
+    // in the south, turning from north towards east lowers the azimuth
+    if crate::simulation::southern_hemisphere(latitude_deg) {
+        -deviation
+    } else {
+        deviation
+    }
+}
+
+/// The compass azimuth for a deviation from facing the equator, the inverse of [`from_equator`]
+pub fn to_azimuth(deviation_deg: f32, latitude_deg: f32) -> f32 {
+    let deviation = if crate::simulation::southern_hemisphere(latitude_deg) {
+        -deviation_deg
+    } else {
+        deviation_deg
+    };
+    (equator_azimuth(latitude_deg) + deviation).rem_euclid(360.)
+}
+
+/// returns efficiency from 0-1 based on orientation deviation from facing the equator (south
+/// in the northern hemisphere) and inclination.
+/// The table is measured for central Europe and does not depend on the latitude.
+pub fn efficiency(south_deviation_deg: f32, inclination_deg: f32) -> f32 {
     let table = [
         [
             86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5, 86.5,
@@ -106,29 +144,57 @@ pub fn efficiency(south_deviation_deg: f32, inclination_deg: f32) -> f32{
     let num_cols = table.len();
     let num_rows = table[0].len();
 
-    let row_index = (((num_rows.saturating_sub(1)) as f32 / 180. * south_deviation_deg) as usize).min(num_rows.saturating_sub(1)).max(0);
-    let col_index = (((num_cols.saturating_sub(1)) as f32 / 90. * inclination_deg) as usize).min(num_cols.saturating_sub(1)).max(0);
+    let row_index = (((num_rows.saturating_sub(1)) as f32 / 180. * south_deviation_deg) as usize)
+        .min(num_rows.saturating_sub(1))
+        .max(0);
+    let col_index = (((num_cols.saturating_sub(1)) as f32 / 90. * inclination_deg) as usize)
+        .min(num_cols.saturating_sub(1))
+        .max(0);
     debug!("row {row_index} col {col_index}, rows {num_rows} cols {num_cols}");
 
-
-    *table.get(col_index).unwrap_or(&table[0]).get(row_index).unwrap_or(&table[0][0])/100.
+    *table
+        .get(col_index)
+        .unwrap_or(&table[0])
+        .get(row_index)
+        .unwrap_or(&table[0][0])
+        / 100.
 }
 
-
-#[test] 
-fn deg()
-
-{
+#[test]
+fn deg() {
     std::env::set_var("RUST_LOG", "info");
     let _ = env_logger::try_init();
-    
-    for deg in (0..=90).step_by(5) {
 
+    for deg in (0..=90).step_by(5) {
         let res = efficiency(deg as f32, deg as f32);
-        info!("res {res}")
+        log::info!("res {res}")
     }
     assert!(efficiency(0., 35.) == 1.);
     assert!(efficiency(40., 35.) == 0.96);
     assert!(efficiency(70., 20.) == 0.89);
     assert!(efficiency(150., 80.) == 0.363);
-}
\ No newline at end of file
+}
+
+#[test]
+fn hemispheres() {
+    // Munich, Sydney and Cape Town
+    assert_eq!(from_equator(180., 48.1), 0.);
+    assert_eq!(from_equator(0., -33.9), 0.);
+    assert_eq!(from_equator(90., 48.1), -90.);
+    assert_eq!(from_equator(90., -33.9), -90.);
+    assert_eq!(from_equator(300., -33.9), 60.);
+    assert_eq!(from_equator(180., -33.9).abs(), 180.);
+    for deviation in [-135., -30., 0., 45., 170.] {
+        for latitude in [48.1, -33.9] {
+            assert_eq!(
+                from_equator(to_azimuth(deviation, latitude), latitude),
+                deviation
+            );
+        }
+    }
+    // a north facing roof in South Africa is as good as a south facing one in Germany
+    assert_eq!(
+        efficiency(from_equator(0., -33.9).abs(), 30.),
+        efficiency(from_equator(180., 51.).abs(), 30.)
+    );
+}
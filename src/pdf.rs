@@ -0,0 +1,455 @@
+//! Renders a [`Report`] as A4 PDF with the standard Helvetica fonts, so nothing needs to be
+//! embedded.
+
+use egui::Color32;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+use crate::{
+    format,
+    report::{Block, Chart, Report, Table},
+    tr,
+};
+
+const WIDTH: f32 = 595.;
+const HEIGHT: f32 = 842.;
+const MARGIN: f32 = 50.;
+/// Lowest line of content, above the footer
+const BOTTOM: f32 = 70.;
+const BODY: f32 = 10.;
+const SMALL: f32 = 8.;
+const LINE: f32 = 14.;
+const GRAY: f32 = 0.45;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn name(&self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"F1"),
+            Font::Bold => Name(b"F2"),
+        }
+    }
+
+    /// Width of `text` in points, from the Helvetica metrics
+    fn width(&self, text: &str, size: f32) -> f32 {
+        let units = text.chars().map(|c| glyph_width(c) as f32).sum::<f32>();
+        let bold = match self {
+            Font::Regular => 1.,
+            // Helvetica-Bold is slightly wider on average
+            Font::Bold => 1.06,
+        };
+        units * bold * size / 1000.
+    }
+}
+
+/// Advance width of Helvetica in 1/1000 em
+fn glyph_width(c: char) -> u16 {
+    const ASCII: [u16; 95] = [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+        722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+        556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+        500, 334, 260, 334, 584,
+    ];
+    match c {
+        ' '..='~' => ASCII[c as usize - 32],
+        'Ä' | 'À'..='Å' => 667,
+        'Ö' | 'Ò'..='Õ' => 778,
+        'Ü' | 'Ù'..='Û' => 722,
+        'ß' => 611,
+        '°' => 400,
+        '²' | '³' => 333,
+        '…' => 1000,
+        '×' => 584,
+        _ => 556,
+    }
+}
+
+/// Encodes `text` for the standard fonts. Characters outside of WinAnsi become "?".
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\u{20}'..='\u{7e}' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            // narrow and thin spaces of number formats
+            '\u{2009}' | '\u{202f}' => b' ',
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Shortens `text` with an ellipsis to fit `width`
+fn truncate(text: &str, font: Font, size: f32, width: f32) -> String {
+    if font.width(text, size) <= width {
+        return text.into();
+    }
+    let mut short = text.to_string();
+    while !short.is_empty() && font.width(&short, size) + font.width("…", size) > width {
+        short.pop();
+    }
+    format!("{}…", short.trim_end())
+}
+
+/// Splits `text` into lines of at most `width`
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{line} {word}")
+            };
+            if font.width(&candidate, size) > width && !line.is_empty() {
+                lines.push(std::mem::replace(&mut line, word.into()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn rgb(color: Color32) -> (f32, f32, f32) {
+    (
+        color.r() as f32 / 255.,
+        color.g() as f32 / 255.,
+        color.b() as f32 / 255.,
+    )
+}
+
+/// Places content top to bottom and starts new pages as needed
+struct Layout {
+    pages: Vec<Content>,
+    /// Baseline of the next line
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![Content::new()],
+            y: HEIGHT - MARGIN,
+        }
+    }
+
+    fn content(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("a layout has a page")
+    }
+
+    /// Starts a new page unless `height` fits on this one. Returns whether it did.
+    fn reserve(&mut self, height: f32) -> bool {
+        if self.y - height < BOTTOM {
+            self.pages.push(Content::new());
+            self.y = HEIGHT - MARGIN;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn text(&mut self, font: Font, size: f32, x: f32, y: f32, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.content()
+            .begin_text()
+            .set_font(font.name(), size)
+            .next_line(x, y)
+            .show(Str(&win_ansi(text)))
+            .end_text();
+    }
+
+    fn text_right(&mut self, font: Font, size: f32, right: f32, y: f32, text: &str) {
+        self.text(font, size, right - font.width(text, size), y, text);
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, gray: f32) {
+        self.content()
+            .set_stroke_gray(gray)
+            .set_line_width(0.5)
+            .move_to(x1, y1)
+            .line_to(x2, y2)
+            .stroke();
+    }
+
+    fn fill(&mut self, x: f32, y: f32, width: f32, height: f32, (r, g, b): (f32, f32, f32)) {
+        self.content()
+            .set_fill_rgb(r, g, b)
+            .rect(x, y, width, height)
+            .fill_nonzero()
+            .set_fill_gray(0.);
+    }
+
+    fn paragraph(&mut self, font: Font, size: f32, text: &str) {
+        for line in wrap(text, font, size, WIDTH - 2. * MARGIN) {
+            self.reserve(LINE);
+            self.y -= LINE;
+            self.text(font, size, MARGIN, self.y, &line);
+        }
+    }
+
+    fn facts(&mut self, facts: &[(String, String)]) {
+        let label_width = 160.;
+        for (label, value) in facts {
+            let lines = wrap(
+                value,
+                Font::Regular,
+                BODY,
+                WIDTH - 2. * MARGIN - label_width,
+            );
+            self.reserve(LINE * lines.len() as f32);
+            let label = truncate(label, Font::Bold, BODY, label_width - 8.);
+            self.text(Font::Bold, BODY, MARGIN, self.y - LINE, &label);
+            for line in lines {
+                self.y -= LINE;
+                self.text(Font::Regular, BODY, MARGIN + label_width, self.y, &line);
+            }
+        }
+    }
+
+    fn table(&mut self, table: &Table) {
+        let size = BODY - 1.;
+        let all_rows = || {
+            std::iter::once(&table.header)
+                .chain(&table.rows)
+                .chain(&table.totals)
+        };
+        let columns = all_rows().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        // numbers get what they need, the first column the rest
+        let mut widths = (0..columns)
+            .map(|c| {
+                all_rows()
+                    .filter_map(|row| row.get(c))
+                    .map(|cell| Font::Bold.width(cell, size) + 12.)
+                    .fold(0., f32::max)
+            })
+            .collect::<Vec<_>>();
+        widths[0] = (WIDTH - 2. * MARGIN - widths[1..].iter().sum::<f32>()).max(120.);
+
+        let row = |layout: &mut Layout, cells: &[String], font: Font| {
+            let mut x = MARGIN;
+            for (c, cell) in cells.iter().enumerate() {
+                if c == 0 {
+                    let cell = truncate(cell, font, size, widths[0] - 8.);
+                    layout.text(font, size, x + 2., layout.y + 4., &cell);
+                } else {
+                    layout.text_right(font, size, x + widths[c] - 2., layout.y + 4., cell);
+                }
+                x += widths[c];
+            }
+        };
+        let header = |layout: &mut Layout| {
+            layout.y -= LINE;
+            row(layout, &table.header, Font::Bold);
+            layout.line(MARGIN, layout.y, WIDTH - MARGIN, layout.y, 0.);
+        };
+
+        self.reserve(3. * LINE);
+        header(self);
+        for (i, cells) in table.rows.iter().enumerate() {
+            if self.reserve(LINE) {
+                header(self);
+            }
+            self.y -= LINE;
+            if i % 2 == 1 {
+                self.fill(
+                    MARGIN,
+                    self.y,
+                    WIDTH - 2. * MARGIN,
+                    LINE,
+                    (0.94, 0.94, 0.94),
+                );
+            }
+            row(self, cells, Font::Regular);
+        }
+        for (i, cells) in table.totals.iter().enumerate() {
+            self.reserve(LINE);
+            if i == 0 {
+                self.line(MARGIN, self.y, WIDTH - MARGIN, self.y, 0.);
+            }
+            self.y -= LINE;
+            row(self, cells, Font::Bold);
+        }
+    }
+
+    fn chart(&mut self, chart: &Chart) {
+        let plot_height = 150.;
+        self.reserve(plot_height + 4. * LINE);
+        self.y -= LINE;
+        let title = format!("{} ({})", chart.title, chart.unit);
+        self.text(Font::Bold, BODY, MARGIN, self.y, &title);
+
+        // legend
+        self.y -= LINE;
+        let mut x = MARGIN;
+        for series in &chart.series {
+            self.fill(x, self.y, 8., 8., rgb(series.color));
+            self.text(Font::Regular, SMALL, x + 12., self.y, &series.name);
+            x += 24. + Font::Regular.width(&series.name, SMALL);
+        }
+
+        let (step, steps) = chart.axis();
+        let top = step * steps as f32;
+
+        let left = MARGIN + 45.;
+        let right = WIDTH - MARGIN;
+        let bottom = self.y - 8. - plot_height;
+        let scale = plot_height / top;
+        for i in 0..=steps {
+            let value = step * i as f32;
+            let y = bottom + value * scale;
+            self.line(left, y, right, y, if i == 0 { 0. } else { 0.85 });
+            self.text_right(
+                Font::Regular,
+                SMALL,
+                left - 4.,
+                y - 3.,
+                &format::number(value, 0),
+            );
+        }
+
+        let groups = chart.labels.len().max(1);
+        let group_width = (right - left) / groups as f32;
+        let bar_width = group_width * 0.8 / chart.series.len().max(1) as f32;
+        for (g, label) in chart.labels.iter().enumerate() {
+            let group_left = left + g as f32 * group_width + group_width * 0.1;
+            for (s, series) in chart.series.iter().enumerate() {
+                let value = series.values.get(g).copied().unwrap_or(0.).max(0.);
+                let x = group_left + s as f32 * bar_width;
+                self.fill(x, bottom, bar_width, value * scale, rgb(series.color));
+            }
+            let center = left + (g as f32 + 0.5) * group_width;
+            let label_width = Font::Regular.width(label, SMALL);
+            self.text(
+                Font::Regular,
+                SMALL,
+                center - label_width / 2.,
+                bottom - 10.,
+                label,
+            );
+        }
+        self.y = bottom - LINE;
+    }
+
+    /// Disclaimer and page number at the bottom of every page
+    fn footers(&mut self, disclaimer: &str) {
+        let pages = self.pages.len();
+        for page in 0..pages {
+            let number = tr!("page_n", page = page + 1, pages = pages);
+            self.pages[page]
+                .set_fill_gray(GRAY)
+                .begin_text()
+                .set_font(Font::Regular.name(), SMALL)
+                .next_line(MARGIN, 36.)
+                .show(Str(&win_ansi(disclaimer)))
+                .end_text()
+                .begin_text()
+                .set_font(Font::Regular.name(), SMALL)
+                .next_line(WIDTH - MARGIN - Font::Regular.width(&number, SMALL), 36.)
+                .show(Str(&win_ansi(&number)))
+                .end_text();
+        }
+    }
+}
+
+/// The report as PDF file
+pub fn render(report: &Report) -> Vec<u8> {
+    let mut layout = Layout::new();
+    layout.y -= 20.;
+    layout.text(Font::Bold, 20., MARGIN, layout.y, &report.title);
+    layout.text_right(
+        Font::Regular,
+        BODY,
+        WIDTH - MARGIN,
+        layout.y,
+        &report.date.to_string(),
+    );
+    layout.y -= 20.;
+    layout.text(Font::Regular, 12., MARGIN, layout.y, &report.subtitle);
+    layout.y -= 10.;
+
+    for section in &report.sections {
+        // keep the heading with the start of the section
+        layout.reserve(5. * LINE);
+        layout.y -= 2. * LINE;
+        layout.text(Font::Bold, 14., MARGIN, layout.y, &section.heading);
+        layout.y -= 4.;
+        layout.line(MARGIN, layout.y, WIDTH - MARGIN, layout.y, GRAY);
+        for block in &section.blocks {
+            layout.y -= 4.;
+            match block {
+                Block::Text(text) => layout.paragraph(Font::Regular, BODY, text),
+                Block::Facts(facts) => layout.facts(facts),
+                Block::Table(table) => layout.table(table),
+                Block::Chart(chart) => layout.chart(chart),
+            }
+        }
+    }
+    layout.footers(&report.disclaimer);
+
+    let catalog_id = Ref::new(1);
+    let tree_id = Ref::new(2);
+    let regular_id = Ref::new(3);
+    let bold_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    let page_ids = (0..layout.pages.len() as i32)
+        .map(|i| (Ref::new(6 + 2 * i), Ref::new(7 + 2 * i)))
+        .collect::<Vec<_>>();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().map(|(page, _)| *page))
+        .count(page_ids.len() as i32);
+    for ((page_id, content_id), content) in page_ids.into_iter().zip(layout.pages) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0., 0., WIDTH, HEIGHT))
+            .parent(tree_id)
+            .contents(content_id);
+        page.resources()
+            .fonts()
+            .pair(Font::Regular.name(), regular_id)
+            .pair(Font::Bold.name(), bold_id);
+        page.finish();
+        pdf.stream(content_id, &content.finish());
+    }
+    for (id, name) in [(regular_id, "Helvetica"), (bold_id, "Helvetica-Bold")] {
+        pdf.type1_font(id)
+            .base_font(Name(name.as_bytes()))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+    pdf.document_info(info_id)
+        .title(TextStr(&format!("{} – {}", report.title, report.subtitle)))
+        .creator(TextStr("pvcalc"));
+    pdf.finish()
+}
+
+#[test]
+fn pdf() {
+    assert_eq!(win_ansi("Gewähr: 12 €"), b"Gew\xe4hr: 12 \x80");
+
+    let pdf = render(&crate::report::sample());
+    assert!(pdf.starts_with(b"%PDF-"));
+    let pages = pdf.windows(9).filter(|w| w == b"/MediaBox").count();
+    assert!(pages > 1);
+}
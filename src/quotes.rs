@@ -0,0 +1,382 @@
+//! Dated supplier quotes for library items. A project is priced with the newest or the
+//! cheapest quote valid on the day; without one, the item's list price is used.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    currency::{currency_edit, Currency, ExchangeRates},
+    tr,
+    units::Money,
+};
+
+/// A calendar day, stored as "YYYY-MM-DD"
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    /// Days since 1970-01-01
+    days: i32,
+}
+
+impl Date {
+    /// From the proleptic Gregorian calendar, see
+    /// <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+    pub fn new(year: i32, month: u32, day: u32) -> Self {
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = month as i32;
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i32 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Self {
+            days: era * 146_097 + day_of_era - 719_468,
+        }
+    }
+
+    /// `day` clamped to the length of the month, e.g. February 31 becomes the 28th or 29th
+    pub fn clamped(year: i32, month: u32, day: u32) -> Self {
+        Self::new(year, month, day.clamp(1, days_in_month(year, month)))
+    }
+
+    /// Year, month and day
+    pub fn ymd(&self) -> (i32, u32, u32) {
+        let z = self.days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = year_of_era + era * 400 + i32::from(month <= 2);
+        (year, month, day)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn today() -> Self {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            days: (seconds / 86_400) as i32,
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn today() -> Self {
+        Self {
+            days: (js_sys::Date::now() / 86_400_000.) as i32,
+        }
+    }
+
+    /// The date `days` later
+    pub fn plus_days(&self, days: i32) -> Date {
+        Date {
+            days: self.days + days,
+        }
+    }
+
+    /// Days from `earlier` to this date
+    pub fn days_since(&self, earlier: Date) -> i32 {
+        self.days - earlier.days
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let parts = s
+            .trim()
+            .splitn(3, '-')
+            .map(|p| p.parse::<u32>().ok())
+            .collect::<Option<Vec<_>>>();
+        match parts.as_deref() {
+            Some(&[year, month @ 1..=12, day])
+                if (1..=days_in_month(year as i32, month)).contains(&day) =>
+            {
+                Ok(Date::new(year as i32, month, day))
+            }
+            _ => Err(format!("Invalid date {s}, expected YYYY-MM-DD")),
+        }
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// An offer for one piece of a library item
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Quote {
+    pub supplier: String,
+    #[serde(with = "crate::units::money")]
+    pub price: Money,
+    /// Quotes in currencies without an exchange rate are not used
+    pub currency: Currency,
+    /// Day of the offer
+    pub date: Date,
+    /// Last day the offer holds, `None` if open
+    pub valid_until: Option<Date>,
+}
+
+impl Default for Quote {
+    fn default() -> Self {
+        Self {
+            supplier: Default::default(),
+            price: Default::default(),
+            currency: Currency::eur(),
+            date: Date::today(),
+            valid_until: None,
+        }
+    }
+}
+
+impl Quote {
+    pub fn is_valid_on(&self, day: Date) -> bool {
+        self.date <= day && self.valid_until.map_or(true, |until| day <= until)
+    }
+}
+
+/// Which quote a project is priced with
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum PriceSelection {
+    /// The newest valid quote
+    #[default]
+    Current,
+    Cheapest,
+}
+
+impl PriceSelection {
+    pub const ALL: [PriceSelection; 2] = [PriceSelection::Current, PriceSelection::Cheapest];
+
+    pub fn label(&self) -> String {
+        match self {
+            PriceSelection::Current => tr!("price_current"),
+            PriceSelection::Cheapest => tr!("price_cheapest"),
+        }
+    }
+}
+
+/// How and on which day items are priced, and in which currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pricing {
+    pub selection: PriceSelection,
+    pub day: Date,
+    pub currency: Currency,
+    pub rates: ExchangeRates,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Self {
+            selection: PriceSelection::default(),
+            day: Date::today(),
+            currency: Currency::default(),
+            rates: ExchangeRates::default(),
+        }
+    }
+}
+
+impl Pricing {
+    /// `money` in the currency of the pricing, if there is an exchange rate
+    pub fn convert(&self, money: Money, currency: &Currency) -> Option<Money> {
+        self.rates.convert(money, currency, &self.currency)
+    }
+
+    /// The quote `quotes` are priced with, if any is valid and can be converted
+    pub fn quote<'a>(&self, quotes: &'a [Quote]) -> Option<&'a Quote> {
+        let valid = quotes.iter().filter_map(|q| {
+            let price = self.convert(q.price, &q.currency)?;
+            q.is_valid_on(self.day).then_some((q, price))
+        });
+        match self.selection {
+            PriceSelection::Current => valid.max_by_key(|(q, _)| q.date),
+            PriceSelection::Cheapest => {
+                valid.min_by(|(_, a), (_, b)| a.amount().total_cmp(&b.amount()))
+            }
+        }
+        .map(|(q, _)| q)
+    }
+
+    /// Price from the quotes, or `list_price` in `currency` if none is valid. `None` if
+    /// there is no exchange rate for it.
+    pub fn price(&self, list_price: Money, currency: &Currency, quotes: &[Quote]) -> Option<Money> {
+        match self.quote(quotes) {
+            Some(quote) => self.convert(quote.price, &quote.currency),
+            None => self.convert(list_price, currency),
+        }
+    }
+
+    /// Whether the item has quotes but none newer than `max_age_days` is valid
+    pub fn is_stale(&self, quotes: &[Quote], max_age_days: u32) -> bool {
+        !quotes.is_empty()
+            && !quotes.iter().any(|q| {
+                q.is_valid_on(self.day) && self.day.days_since(q.date) <= max_age_days as i32
+            })
+    }
+}
+
+/// `price` without the VAT at `vat_rate` it includes
+pub fn net(price: Money, vat_rate: f32) -> Money {
+    price / (1. + vat_rate)
+}
+
+/// Year, month and day
+pub fn date_edit(ui: &mut egui::Ui, date: &mut Date) {
+    let (mut year, mut month, mut day) = date.ymd();
+    let changed = ui
+        .add(egui::DragValue::new(&mut year).clamp_range(2000..=2100))
+        .changed()
+        | ui.add(egui::DragValue::new(&mut month).clamp_range(1..=12))
+            .changed()
+        | ui.add(egui::DragValue::new(&mut day).clamp_range(1..=days_in_month(year, month)))
+            .changed();
+    if changed {
+        *date = Date::clamped(year, month, day);
+    }
+}
+
+/// Editor for the quotes of a library item
+pub fn quotes_ui(ui: &mut egui::Ui, quotes: &mut Vec<Quote>) {
+    let mut delete = None;
+    for (i, quote) in quotes.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut quote.supplier)
+                        .hint_text(tr!("supplier"))
+                        .desired_width(90.),
+                );
+                ui.add(quote.price.drag_in(&quote.currency).speed(0.1));
+                currency_edit(ui, &mut quote.currency);
+                if ui
+                    .small_button(egui_phosphor::regular::TRASH_SIMPLE)
+                    .clicked()
+                {
+                    delete = Some(i);
+                }
+            });
+            ui.horizontal(|ui| {
+                date_edit(ui, &mut quote.date);
+                let mut limited = quote.valid_until.is_some();
+                if ui.checkbox(&mut limited, tr!("valid_until")).changed() {
+                    quote.valid_until = limited.then(|| Date::new(quote.date.ymd().0, 12, 31));
+                }
+                if let Some(until) = &mut quote.valid_until {
+                    date_edit(ui, until);
+                }
+            });
+        });
+    }
+    if let Some(i) = delete {
+        quotes.remove(i);
+    }
+    if ui
+        .small_button(format!(
+            "{} {}",
+            egui_phosphor::regular::PLUS,
+            tr!("add_quote")
+        ))
+        .clicked()
+    {
+        quotes.push(Quote::default());
+    }
+}
+
+#[test]
+fn quotes() {
+    let day = Date::new(2024, 3, 1);
+    assert_eq!(day.to_string(), "2024-03-01");
+    assert_eq!("2024-03-01".parse::<Date>(), Ok(day));
+    assert_eq!(Date::new(1970, 1, 1).days, 0);
+    assert_eq!(day.days_since(Date::new(2023, 3, 1)), 366);
+    assert_eq!(Date::new(2000, 2, 29).ymd(), (2000, 2, 29));
+    assert!("2024-13-01".parse::<Date>().is_err());
+    assert!("2024-02-31".parse::<Date>().is_err());
+    assert!("2023-02-29".parse::<Date>().is_err());
+    assert!("2024-04-31".parse::<Date>().is_err());
+    assert_eq!("2024-02-29".parse::<Date>(), Ok(Date::new(2024, 2, 29)));
+    assert_eq!(Date::clamped(2024, 2, 31), Date::new(2024, 2, 29));
+    assert_eq!(Date::clamped(2023, 2, 31), Date::new(2023, 2, 28));
+    assert_eq!(Date::clamped(1900, 2, 29), Date::new(1900, 2, 28));
+    assert_eq!(Date::clamped(2024, 6, 31), Date::new(2024, 6, 30));
+
+    let quote = |supplier: &str, price, date: &str, until: Option<&str>| Quote {
+        supplier: supplier.into(),
+        price: Money::from_amount(price),
+        currency: Currency::eur(),
+        date: date.parse().unwrap(),
+        valid_until: until.map(|u| u.parse().unwrap()),
+    };
+    let quotes = [
+        quote("A", 200., "2023-06-01", None),
+        quote("B", 180., "2024-01-10", Some("2024-02-01")),
+        quote("C", 190., "2024-02-15", None),
+        quote("D", 150., "2024-04-01", None),
+    ];
+    let list = Money::from_amount(250.);
+    let mut pricing = Pricing {
+        selection: PriceSelection::Current,
+        day,
+        ..Default::default()
+    };
+    let eur = Currency::eur();
+    assert_eq!(pricing.price(list, &eur, &quotes).unwrap().amount(), 190.);
+    pricing.selection = PriceSelection::Cheapest;
+    assert_eq!(pricing.price(list, &eur, &quotes).unwrap().amount(), 190.);
+    pricing.day = Date::new(2024, 1, 20);
+    assert_eq!(pricing.price(list, &eur, &quotes).unwrap().amount(), 180.);
+    pricing.day = Date::new(2023, 1, 1);
+    assert_eq!(pricing.price(list, &eur, &quotes).unwrap().amount(), 250.);
+
+    // a project in francs, quotes in currencies without a rate are ignored
+    let chf = Currency("CHF".into());
+    pricing.currency = chf.clone();
+    pricing.rates = ExchangeRates(vec![crate::currency::ExchangeRate {
+        currency: chf,
+        per_eur: 0.95,
+        date: day,
+    }]);
+    let usd = Quote {
+        currency: Currency("USD".into()),
+        ..quote("E", 100., "2022-01-01", None)
+    };
+    assert!((pricing.price(list, &eur, &[usd.clone()]).unwrap().amount() - 237.5).abs() < 0.01);
+    // nor is a list price
+    assert_eq!(pricing.price(list, &usd.currency.clone(), &[usd]), None);
+
+    pricing.day = day;
+    assert!(!pricing.is_stale(&quotes, 30));
+    assert!(pricing.is_stale(&quotes[..2], 90));
+    assert!(!pricing.is_stale(&[], 90));
+}
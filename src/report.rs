@@ -0,0 +1,519 @@
+//! Offers for customers: a project and its analysis laid out as a document of sections,
+//! which [`crate::pdf`], [`crate::html`] and [`crate::markdown`] render.
+
+use egui::Color32;
+
+use crate::{
+    analysis::{replacement_years, Analysis},
+    charts::{CONSUMPTION, FEED_IN, GRID_IMPORT, PRODUCTION, SELF_CONSUMPTION},
+    components::{Library, Project},
+    format, html, markdown, pdf,
+    quotes::Date,
+    simulation::{month_name, Month},
+    tax::CostCategory,
+    tr,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub title: String,
+    /// Name of the scenario
+    pub subtitle: String,
+    pub date: Date,
+    pub sections: Vec<Section>,
+    /// Printed on every page
+    pub disclaimer: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub heading: String,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Text(String),
+    /// Label and value pairs. Values may span several lines.
+    Facts(Vec<(String, String)>),
+    Table(Table),
+    Chart(Chart),
+}
+
+/// The first column is text, the others are numbers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Sums below the rows
+    pub totals: Vec<Vec<String>>,
+}
+
+/// Grouped bars, one group per label
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub title: String,
+    /// Unit of the values, e.g. "kWh"
+    pub unit: String,
+    pub labels: Vec<String>,
+    pub series: Vec<Series>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub color: Color32,
+    pub values: Vec<f32>,
+}
+
+impl Chart {
+    /// Step between the grid lines and their count, so that the largest value fits
+    pub fn axis(&self) -> (f32, usize) {
+        let max = self
+            .series
+            .iter()
+            .flat_map(|s| &s.values)
+            .fold(0_f32, |max, v| max.max(*v));
+        let step = nice(max / 4.);
+        (step, (max / step).ceil().max(1.) as usize)
+    }
+}
+
+/// Rounds `value` up to 1, 2 or 5 times a power of ten, for axis steps
+fn nice(value: f32) -> f32 {
+    if value <= 0. || !value.is_finite() {
+        return 1.;
+    }
+    let magnitude = 10_f32.powf(value.log10().floor());
+    [1., 2., 5., 10.]
+        .into_iter()
+        .map(|f| f * magnitude)
+        .find(|step| *step >= value)
+        .unwrap_or(10. * magnitude)
+}
+
+impl Report {
+    /// The offer for `project`, with money in the project's currency
+    pub fn new(project: &Project, library: &Library, analysis: &Analysis) -> Self {
+        Self {
+            title: tr!("report_title"),
+            subtitle: project.name.clone(),
+            date: Date::today(),
+            sections: vec![
+                customer(project),
+                overview(project, library, analysis),
+                bill_of_materials(project, library),
+                yield_and_losses(project, analysis),
+                monthly(&analysis.monthly),
+                economics(project, library, analysis),
+            ],
+            disclaimer: tr!("disclaimer"),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    #[default]
+    Pdf,
+    /// One file with inline SVG charts
+    Html,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Pdf,
+        ExportFormat::Html,
+        ExportFormat::Markdown,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "PDF",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Markdown => "Markdown",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "application/pdf",
+            ExportFormat::Html => "text/html",
+            ExportFormat::Markdown => "text/markdown",
+        }
+    }
+
+    pub fn render(&self, report: &Report) -> Vec<u8> {
+        match self {
+            ExportFormat::Pdf => pdf::render(report),
+            ExportFormat::Html => html::render(report).into_bytes(),
+            ExportFormat::Markdown => markdown::render(report).into_bytes(),
+        }
+    }
+}
+
+/// A file name for the report of `project`, without extension
+pub fn file_name(project: &Project) -> String {
+    let name = project
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    match name.trim_matches('_') {
+        "" => "report".into(),
+        name => name.into(),
+    }
+}
+
+fn customer(project: &Project) -> Section {
+    let customer = &project.customer;
+    let facts = [
+        (tr!("name"), customer.name.clone()),
+        (tr!("address"), customer.address.trim().to_string()),
+        (tr!("email"), customer.email.clone()),
+        (tr!("phone"), customer.phone.clone()),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .collect();
+    Section {
+        heading: tr!("customer"),
+        blocks: vec![Block::Facts(facts)],
+    }
+}
+
+fn overview(project: &Project, library: &Library, analysis: &Analysis) -> Section {
+    let inverters = project
+        .inverters
+        .iter()
+        .filter_map(|id| library.inverters.get(*id))
+        .map(|i| format!("{} {}", i.brand, i.model).trim().to_string())
+        .collect::<Vec<_>>();
+    let storage = project.storage(library);
+
+    let mut facts = vec![
+        (
+            tr!("panels"),
+            tr!(
+                "panels_installed",
+                count = analysis.panel_count,
+                area = format::area(analysis.area_m2)
+            ),
+        ),
+        (tr!("peak_power"), format::kwp(analysis.kwp)),
+        (tr!("inverters"), inverters.join("\n")),
+    ];
+    if storage.capacity_kwh > 0. {
+        facts.push((
+            tr!("usable_capacity"),
+            format!("{} kWh", format::number(storage.capacity_kwh, 1)),
+        ));
+    }
+    facts.extend([
+        (
+            tr!("orientation"),
+            format!("{}°", format::number(project.azimuth_deg, 0)),
+        ),
+        (
+            tr!("tilt"),
+            format!("{}°", format::number(project.panel_angle_deg, 0)),
+        ),
+        (
+            tr!("latitude"),
+            format!("{}°", format::number(project.latitude_deg, 1)),
+        ),
+        (tr!("efficiency"), format::percent(analysis.efficiency)),
+        (tr!("yield_per_year"), format::kwh(analysis.yield_year_kwh)),
+        (tr!("consumption"), format::kwh(project.consumption.kwh())),
+    ]);
+    facts.retain(|(_, value)| !value.is_empty());
+    Section {
+        heading: tr!("system_overview"),
+        blocks: vec![Block::Facts(facts)],
+    }
+}
+
+fn bill_of_materials(project: &Project, library: &Library) -> Section {
+    let price = |value: f32| format::price(value, &project.currency);
+    let lines = project.bill_of_materials(library);
+    let net = lines.iter().map(|l| l.total.amount()).sum::<f32>();
+    let groups = project.tax.vat_groups(&lines);
+    let vat = groups.iter().map(|g| g.vat.amount()).sum::<f32>();
+    let total = |label: String, value: f32| vec![label, String::new(), String::new(), price(value)];
+    Section {
+        heading: tr!("bill_of_materials"),
+        blocks: vec![Block::Table(Table {
+            header: vec![
+                tr!("description"),
+                tr!("quantity"),
+                tr!("unit_price"),
+                tr!("total"),
+            ],
+            rows: lines
+                .into_iter()
+                .map(|line| {
+                    vec![
+                        line.description,
+                        format!("{} {}", format::number(line.quantity, 1), line.unit),
+                        price(line.unit_price.amount()),
+                        price(line.total.amount()),
+                    ]
+                })
+                .collect(),
+            totals: std::iter::once(total(tr!("net_total"), net))
+                .chain(groups.iter().map(|g| {
+                    total(
+                        tr!("vat_value", percent = format::percent(g.rate)),
+                        g.vat.amount(),
+                    )
+                }))
+                .chain([total(tr!("gross_total"), net + vat)])
+                .collect(),
+        })],
+    }
+}
+
+fn yield_and_losses(project: &Project, analysis: &Analysis) -> Section {
+    let reference = analysis.kwp * project.yield_kwh_kwp;
+    let produced = analysis.yield_year_kwh;
+    // what is neither used, fed in nor curtailed was lost charging and discharging
+    let battery_loss = (produced
+        - analysis.curtailed_kwh
+        - analysis.consumption_covered_kwh
+        - analysis.amount_to_sell_kwh)
+        .max(0.);
+    let row = |label: String, kwh: f32| {
+        let share = if reference > 0. { kwh / reference } else { 0. };
+        vec![label, format::kwh(kwh), format::percent(share)]
+    };
+    Section {
+        heading: tr!("yield_and_losses"),
+        blocks: vec![
+            Block::Table(Table {
+                header: vec![String::new(), tr!("energy"), tr!("share")],
+                rows: vec![
+                    row(tr!("reference_yield"), reference),
+                    row(tr!("orientation_loss"), produced - reference),
+                    row(tr!("curtailment_loss"), -analysis.curtailed_kwh),
+                    row(tr!("battery_loss"), -battery_loss),
+                ],
+                totals: vec![row(
+                    tr!("usable_yield"),
+                    analysis.consumption_covered_kwh + analysis.amount_to_sell_kwh,
+                )],
+            }),
+            Block::Facts(vec![
+                (
+                    tr!("self_consumption"),
+                    format!(
+                        "{} ({})",
+                        format::kwh(analysis.consumption_covered_kwh),
+                        format::percent(analysis.self_consumption)
+                    ),
+                ),
+                (tr!("feed_in"), format::kwh(analysis.amount_to_sell_kwh)),
+                (tr!("grid_import"), format::kwh(analysis.grid_import_kwh)),
+                (tr!("autarky"), format::percent(analysis.autarky)),
+            ]),
+        ],
+    }
+}
+
+fn monthly(months: &[Month]) -> Section {
+    let series = |name: String, color: Color32, value: fn(&Month) -> f32| Series {
+        name,
+        color,
+        values: months.iter().map(value).collect(),
+    };
+    let labels = (0..months.len())
+        .map(|m| month_name(m).chars().take(3).collect())
+        .collect::<Vec<_>>();
+    Section {
+        heading: tr!("monthly_overview"),
+        blocks: vec![
+            Block::Chart(Chart {
+                title: tr!("production_and_consumption"),
+                unit: "kWh".into(),
+                labels: labels.clone(),
+                series: vec![
+                    series(tr!("production"), PRODUCTION, |m| m.production_kwh),
+                    series(tr!("consumption"), CONSUMPTION, |m| m.consumption_kwh),
+                    series(tr!("self_consumption"), SELF_CONSUMPTION, |m| {
+                        m.self_consumption_kwh
+                    }),
+                ],
+            }),
+            Block::Chart(Chart {
+                title: tr!("grid_exchange"),
+                unit: "kWh".into(),
+                labels,
+                series: vec![
+                    series(tr!("feed_in"), FEED_IN, |m| m.feed_in_kwh),
+                    series(tr!("grid_import"), GRID_IMPORT, |m| m.grid_import_kwh),
+                ],
+            }),
+        ],
+    }
+}
+
+fn economics(project: &Project, library: &Library, analysis: &Analysis) -> Section {
+    let money = |value: f32| format::money(value, &project.currency);
+    let years = project.lifetime_years.max(0.).ceil() as usize;
+    // battery replacements, due in the year they fall into
+    let mut expenses = vec![0.; years + 1];
+    let pricing = project.pricing(library);
+    for battery in project.batteries(library) {
+        let cost = project
+            .tax
+            .cost(
+                battery.bank_price(&pricing).unwrap_or_default(),
+                CostCategory::Batteries,
+            )
+            .amount();
+        let life = battery.life_years(analysis.battery_cycles_per_year);
+        for year in replacement_years(life, project.lifetime_years) {
+            expenses[(year.ceil() as usize).min(years)] += cost;
+        }
+    }
+
+    let mut cumulative = -analysis.price_sum;
+    let mut rows = vec![vec![
+        "0".into(),
+        String::new(),
+        money(analysis.price_sum),
+        money(cumulative),
+    ]];
+    for (year, expense) in expenses.iter().enumerate().skip(1) {
+        cumulative += analysis.combined_benefit - expense;
+        rows.push(vec![
+            year.to_string(),
+            money(analysis.combined_benefit),
+            if *expense > 0. {
+                money(*expense)
+            } else {
+                String::new()
+            },
+            money(cumulative),
+        ]);
+    }
+
+    let investment = if project.tax.vat_deductible {
+        tr!("net_value", value = money(analysis.price_sum))
+    } else {
+        tr!("gross_value", value = money(analysis.price_sum))
+    };
+    let mut facts = vec![
+        (tr!("investment"), investment),
+        (tr!("benefit_per_year"), money(analysis.combined_benefit)),
+    ];
+    if analysis.income_tax > 0. {
+        facts.push((tr!("income_tax_per_year"), money(analysis.income_tax)));
+    }
+    facts.extend([
+        (
+            tr!("payback"),
+            tr!("years_value", years = format::years(analysis.payback_years)),
+        ),
+        (tr!("npv"), money(analysis.npv)),
+    ]);
+    let mut blocks = vec![Block::Facts(facts)];
+    if analysis.battery_cycles_per_year > 0. {
+        blocks.push(Block::Text(match analysis.battery_replacement_year {
+            Some(year) => tr!(
+                "battery_replacement",
+                year = format::years(year),
+                cost = money(analysis.battery_replacement_cost)
+            ),
+            None => tr!("battery_lasts"),
+        }));
+    }
+    blocks.push(Block::Table(Table {
+        header: vec![
+            tr!("year"),
+            tr!("savings_and_income"),
+            tr!("expenses"),
+            tr!("cash_flow"),
+        ],
+        rows,
+        totals: vec![],
+    }));
+    Section {
+        heading: tr!("economics"),
+        blocks,
+    }
+}
+
+/// A report of a system with battery, for tests of the renderers
+#[cfg(test)]
+pub(crate) fn sample() -> Report {
+    use crate::{
+        analysis::analyze,
+        components::Customer,
+        tax::{Tax, VatRates},
+    };
+
+    let library = Library::default();
+    let project = Project {
+        name: "Mit <Speicher>".into(),
+        panels: vec![0; 10],
+        batteries: vec![0],
+        inverters: vec![1],
+        price_date: Some(Date::new(2024, 3, 1)),
+        panel_angle_deg: 35.,
+        customer: Customer {
+            name: "Erika Mustermann".into(),
+            address: "Heidestraße 17\n51147 Köln".into(),
+            ..Default::default()
+        },
+        tax: Tax {
+            vat: VatRates {
+                installation: 0.19,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    Report::new(&project, &library, &analyze(&project, &library))
+}
+
+#[test]
+fn axis() {
+    assert_eq!(nice(180.), 200.);
+    assert_eq!(nice(0.3), 0.5);
+    let chart = Chart {
+        title: String::new(),
+        unit: "kWh".into(),
+        labels: vec!["Jan".into()],
+        series: vec![Series {
+            name: String::new(),
+            color: Color32::BLACK,
+            values: vec![730.],
+        }],
+    };
+    assert_eq!(chart.axis(), (200., 4));
+}
+
+#[test]
+fn report_currency() {
+    use crate::{analysis::analyze, currency::Currency};
+
+    let library = Library::default();
+    let project = Project {
+        panels: vec![0; 4],
+        currency: Currency("CHF".into()),
+        ..Default::default()
+    };
+    let report = Report::new(&project, &library, &analyze(&project, &library));
+    let markdown = markdown::render(&report);
+    assert!(markdown.contains(" CHF"));
+}
@@ -0,0 +1,542 @@
+//! Hourly simulation of a year: PV production, household load and battery.
+//!
+//! Monthly PV and load totals follow typical central European shares, shifted by half a
+//! year for sites in the southern hemisphere. Within a month the PV yield is spread over the
+//! days using a synthetic reference weather year and over the hours following the sun
+//! between sunrise and sunset at the project's latitude.
+
+use serde::{Deserialize, Serialize};
+
+use crate::tr;
+
+/// Name of a zero based month
+pub fn month_name(month: usize) -> String {
+    match month {
+        0 => tr!("month_1"),
+        1 => tr!("month_2"),
+        2 => tr!("month_3"),
+        3 => tr!("month_4"),
+        4 => tr!("month_5"),
+        5 => tr!("month_6"),
+        6 => tr!("month_7"),
+        7 => tr!("month_8"),
+        8 => tr!("month_9"),
+        9 => tr!("month_10"),
+        10 => tr!("month_11"),
+        _ => tr!("month_12"),
+    }
+}
+
+pub const DAYS_IN_MONTH: [u32; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// June and December solstice, zero based day of year
+const JUNE_SOLSTICE: usize = 171;
+const DECEMBER_SOLSTICE: usize = 354;
+
+/// Share of the yearly PV yield per month for a south facing array in central Europe
+const PRODUCTION_SHARE: [f32; 12] = [
+    3.0, 5.0, 8.5, 11.0, 12.5, 12.5, 12.5, 11.5, 9.0, 6.5, 3.5, 2.5,
+];
+
+/// Share of the yearly household consumption per month (BDEW standard load profile H0)
+const CONSUMPTION_SHARE: [f32; 12] = [9.5, 8.5, 8.8, 7.9, 7.7, 7.2, 7.3, 7.4, 7.6, 8.3, 8.9, 9.9];
+
+/// Relative household consumption per hour of the day (H0)
+const LOAD_PROFILE: [f32; 24] = [
+    2.6, 2.1, 1.9, 1.8, 1.8, 2.1, 3.2, 4.5, 4.9, 4.6, 4.5, 4.8, 5.4, 5.2, 4.4, 4.0, 4.1, 4.8, 5.9,
+    6.6, 6.5, 5.8, 4.6, 3.5,
+];
+
+/// Storage as seen by the simulation
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Storage {
+    /// Usable capacity
+    pub capacity_kwh: f32,
+    pub max_charge_kw: f32,
+    pub max_discharge_kw: f32,
+    /// 0-1, round trip
+    pub efficiency: f32,
+}
+
+/// What the simulation needs to know about a project
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimulationInput {
+    pub production_year_kwh: f32,
+    pub consumption_year_kwh: f32,
+    pub latitude_deg: f32,
+    /// Compass direction the panels face, 0-360°
+    pub azimuth_deg: f32,
+    pub tilt_deg: f32,
+    pub storage: Storage,
+    /// Maximum power fed into the grid, the rest is curtailed
+    pub feed_in_limit_kw: Option<f32>,
+}
+
+/// Energy flows of one hour. All values in kWh, which equals the average power in kW.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+#[serde(default)]
+pub struct Hour {
+    pub production_kwh: f32,
+    pub consumption_kwh: f32,
+    /// Consumption covered by PV, directly or from the battery
+    pub self_consumption_kwh: f32,
+    pub battery_charge_kwh: f32,
+    pub battery_discharge_kwh: f32,
+    /// Battery state of charge at the end of the hour
+    pub soc_kwh: f32,
+    pub feed_in_kwh: f32,
+    /// PV energy lost to the feed-in limit
+    pub curtailed_kwh: f32,
+    pub grid_import_kwh: f32,
+}
+
+/// Energy flows of one month
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Month {
+    pub production_kwh: f32,
+    pub consumption_kwh: f32,
+    /// Consumption covered by PV, directly or from the battery
+    pub self_consumption_kwh: f32,
+    pub feed_in_kwh: f32,
+    pub curtailed_kwh: f32,
+    pub grid_import_kwh: f32,
+    /// Production per hour of an average day of this month
+    pub daily_production_kwh: Vec<f32>,
+    /// Consumption per hour of an average day of this month
+    pub daily_consumption_kwh: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum DayKind {
+    #[default]
+    SummerSolstice,
+    WinterSolstice,
+    /// Of the synthetic reference weather year, not of measured weather
+    Best,
+    Worst,
+}
+
+impl DayKind {
+    pub const ALL: [DayKind; 4] = [
+        DayKind::SummerSolstice,
+        DayKind::WinterSolstice,
+        DayKind::Best,
+        DayKind::Worst,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            DayKind::SummerSolstice => tr!("summer_solstice"),
+            DayKind::WinterSolstice => tr!("winter_solstice"),
+            DayKind::Best => tr!("best_day"),
+            DayKind::Worst => tr!("worst_day"),
+        }
+    }
+}
+
+/// The hours of a single day worth looking at
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Day {
+    pub kind: DayKind,
+    /// Zero based day of year
+    pub day_of_year: usize,
+    pub hours: Vec<Hour>,
+}
+
+/// Normalizes `values` so they sum up to 1
+fn shares<const N: usize>(values: [f32; N]) -> [f32; N] {
+    let sum = values.iter().sum::<f32>();
+    values.map(|v| if sum > 0. { v / sum } else { 0. })
+}
+
+/// Month index of a zero based day of year
+pub(crate) fn month_of(day_of_year: usize) -> usize {
+    let mut first = 0;
+    for (m, days) in DAYS_IN_MONTH.iter().enumerate() {
+        first += *days as usize;
+        if day_of_year < first {
+            return m;
+        }
+    }
+    11
+}
+
+/// Relative PV power per hour of the day, following the sun between sunrise and sunset.
+/// Panels facing east produce more in the morning, panels facing west in the evening.
+fn sun_profile(day_of_year: usize, input: &SimulationInput) -> [f32; 24] {
+    let latitude_deg = input.latitude_deg;
+    let declination = 23.45_f32.to_radians()
+        * (360. / 365. * (284. + day_of_year as f32 + 1.))
+            .to_radians()
+            .sin();
+    let latitude = latitude_deg.to_radians();
+    let cos_sunset = (-latitude.tan() * declination.tan()).clamp(-1., 1.);
+    let day_length_h = 2. * cos_sunset.acos().to_degrees() / 15.;
+    let sunrise = 12. - day_length_h / 2.;
+
+    let mut profile = [0.; 24];
+    for (hour, p) in profile.iter_mut().enumerate() {
+        let t = (hour as f32 + 0.5 - sunrise) / day_length_h;
+        if (0. ..=1.).contains(&t) {
+            // the sun moves from east over the equator side to west
+            let sun_azimuth = if southern_hemisphere(latitude_deg) {
+                90. - t * 180.
+            } else {
+                90. + t * 180.
+            };
+            let facing = (sun_azimuth - input.azimuth_deg).to_radians().cos()
+                * input.tilt_deg.clamp(0., 90.).to_radians().sin();
+            *p = (t * std::f32::consts::PI).sin() * (1. + facing).max(0.);
+        }
+    }
+    shares(profile)
+}
+
+/// Synthetic reference weather year: clearness per day from 0.1 (overcast) to 1 (clear sky).
+/// Deterministic, so results are reproducible.
+pub fn reference_weather() -> Vec<f32> {
+    let mut state: u32 = 0x2545_f491;
+    let mut clearness = 0.6;
+    (0..365)
+        .map(|_| {
+            // xorshift
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let random = state as f32 / u32::MAX as f32;
+            // weather tends to stay for a few days
+            clearness = 0.5 * clearness + 0.5 * (0.1 + 0.9 * random);
+            clearness
+        })
+        .collect()
+}
+
+/// Moves energy between PV, load, battery and grid for one hour
+fn step(
+    production_kwh: f32,
+    consumption_kwh: f32,
+    input: &SimulationInput,
+    soc_kwh: &mut f32,
+) -> Hour {
+    let storage = &input.storage;
+    // losses are split evenly between charging and discharging
+    let efficiency = storage.efficiency.clamp(0.01, 1.).sqrt();
+    let direct = production_kwh.min(consumption_kwh);
+    let mut hour = Hour {
+        production_kwh,
+        consumption_kwh,
+        ..Default::default()
+    };
+
+    if production_kwh > consumption_kwh {
+        let surplus = production_kwh - consumption_kwh;
+        let charge = surplus
+            .min(storage.max_charge_kw)
+            .min((storage.capacity_kwh - *soc_kwh).max(0.) / efficiency);
+        *soc_kwh = (*soc_kwh + charge * efficiency).min(storage.capacity_kwh);
+        hour.battery_charge_kwh = charge;
+        hour.feed_in_kwh = surplus - charge;
+        if let Some(limit) = input.feed_in_limit_kw {
+            hour.curtailed_kwh = (hour.feed_in_kwh - limit.max(0.)).max(0.);
+            hour.feed_in_kwh -= hour.curtailed_kwh;
+        }
+    } else {
+        let deficit = consumption_kwh - production_kwh;
+        let discharge = deficit
+            .min(storage.max_discharge_kw)
+            .min(*soc_kwh * efficiency);
+        *soc_kwh = (*soc_kwh - discharge / efficiency).max(0.);
+        hour.battery_discharge_kwh = discharge;
+        hour.grid_import_kwh = deficit - discharge;
+    }
+    hour.self_consumption_kwh = direct + hour.battery_discharge_kwh;
+    hour.soc_kwh = *soc_kwh;
+    hour
+}
+
+pub fn southern_hemisphere(latitude_deg: f32) -> bool {
+    latitude_deg < 0.
+}
+
+/// Summer and winter solstice at the latitude, zero based day of year
+pub fn solstices(latitude_deg: f32) -> (usize, usize) {
+    if southern_hemisphere(latitude_deg) {
+        (DECEMBER_SOLSTICE, JUNE_SOLSTICE)
+    } else {
+        (JUNE_SOLSTICE, DECEMBER_SOLSTICE)
+    }
+}
+
+/// Monthly shares given for the northern hemisphere, moved by half a year in the south
+fn seasonal(values: [f32; 12], latitude_deg: f32) -> [f32; 12] {
+    let shift = if southern_hemisphere(latitude_deg) {
+        6
+    } else {
+        0
+    };
+    let values = shares(values);
+    std::array::from_fn(|m| values[(m + shift) % 12])
+}
+
+/// PV and load per hour of the year, before the battery is considered
+fn profiles(input: &SimulationInput, weather: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let production_share = seasonal(PRODUCTION_SHARE, input.latitude_deg);
+    let consumption_share = seasonal(CONSUMPTION_SHARE, input.latitude_deg);
+    let load_profile = shares(LOAD_PROFILE);
+
+    let mut clearness_per_month = [0.; 12];
+    for (day, clearness) in weather.iter().enumerate() {
+        clearness_per_month[month_of(day)] += clearness;
+    }
+
+    let mut production = Vec::with_capacity(365 * 24);
+    let mut consumption = Vec::with_capacity(365 * 24);
+    for (day, clearness) in weather.iter().enumerate() {
+        let m = month_of(day);
+        let production_day =
+            input.production_year_kwh * production_share[m] * clearness / clearness_per_month[m];
+        let consumption_day =
+            input.consumption_year_kwh * consumption_share[m] / DAYS_IN_MONTH[m] as f32;
+        let sun = sun_profile(day, input);
+        for hour in 0..24 {
+            production.push(sun[hour] * production_day);
+            consumption.push(load_profile[hour] * consumption_day);
+        }
+    }
+    (production, consumption)
+}
+
+/// Simulates every hour of the reference year
+pub fn simulate_year(input: &SimulationInput) -> Vec<Hour> {
+    let (production, consumption) = profiles(input, &reference_weather());
+    let mut soc = 0.;
+    production
+        .iter()
+        .zip(&consumption)
+        .map(|(p, c)| step(*p, *c, input, &mut soc))
+        .collect()
+}
+
+/// Sums up the hours of each month
+pub fn monthly(hours: &[Hour]) -> Vec<Month> {
+    let mut months = vec![
+        Month {
+            daily_production_kwh: vec![0.; 24],
+            daily_consumption_kwh: vec![0.; 24],
+            ..Default::default()
+        };
+        12
+    ];
+    for (i, hour) in hours.iter().enumerate() {
+        let m = month_of(i / 24);
+        let month = &mut months[m];
+        month.production_kwh += hour.production_kwh;
+        month.consumption_kwh += hour.consumption_kwh;
+        month.self_consumption_kwh += hour.self_consumption_kwh;
+        month.feed_in_kwh += hour.feed_in_kwh;
+        month.curtailed_kwh += hour.curtailed_kwh;
+        month.grid_import_kwh += hour.grid_import_kwh;
+        let days = DAYS_IN_MONTH[m] as f32;
+        month.daily_production_kwh[i % 24] += hour.production_kwh / days;
+        month.daily_consumption_kwh[i % 24] += hour.consumption_kwh / days;
+    }
+    months
+}
+
+/// The clear sky solstices and the best and worst day of the synthetic reference weather year
+pub fn typical_days(input: &SimulationInput, hours: &[Hour]) -> Vec<Day> {
+    let production_of = |day: usize| -> f32 {
+        hours[day * 24..(day + 1) * 24]
+            .iter()
+            .map(|h| h.production_kwh)
+            .sum()
+    };
+    let days = hours.len() / 24;
+    if days == 0 {
+        return vec![];
+    }
+    let best = (0..days)
+        .max_by(|a, b| production_of(*a).total_cmp(&production_of(*b)))
+        .unwrap_or_default();
+    let worst = (0..days)
+        .min_by(|a, b| production_of(*a).total_cmp(&production_of(*b)))
+        .unwrap_or_default();
+
+    // the same year, but with a clear sky on the solstices
+    let (summer_solstice, winter_solstice) = solstices(input.latitude_deg);
+    let mut weather = reference_weather();
+    weather[summer_solstice] = 1.;
+    weather[winter_solstice] = 1.;
+    let (production, consumption) = profiles(input, &weather);
+    let clear_day = |day: usize| -> Vec<Hour> {
+        let mut soc = if day > 0 {
+            hours[day * 24 - 1].soc_kwh
+        } else {
+            0.
+        };
+        (day * 24..(day + 1) * 24)
+            .map(|i| step(production[i], consumption[i], input, &mut soc))
+            .collect()
+    };
+    let day = |kind, day_of_year| Day {
+        kind,
+        day_of_year,
+        hours: hours[day_of_year * 24..(day_of_year + 1) * 24].to_vec(),
+    };
+
+    vec![
+        Day {
+            kind: DayKind::SummerSolstice,
+            day_of_year: summer_solstice,
+            hours: clear_day(summer_solstice),
+        },
+        Day {
+            kind: DayKind::WinterSolstice,
+            day_of_year: winter_solstice,
+            hours: clear_day(winter_solstice),
+        },
+        day(DayKind::Best, best),
+        day(DayKind::Worst, worst),
+    ]
+}
+
+#[cfg(test)]
+fn input(capacity_kwh: f32) -> SimulationInput {
+    SimulationInput {
+        production_year_kwh: 4000.,
+        consumption_year_kwh: 2500.,
+        latitude_deg: 51.,
+        azimuth_deg: 180.,
+        tilt_deg: 35.,
+        storage: Storage {
+            capacity_kwh,
+            max_charge_kw: capacity_kwh / 2.,
+            max_discharge_kw: capacity_kwh / 2.,
+            efficiency: 0.9,
+        },
+        feed_in_limit_kw: None,
+    }
+}
+
+#[test]
+fn months() {
+    let months = monthly(&simulate_year(&input(0.)));
+    let sum = |f: fn(&Month) -> f32| months.iter().map(f).sum::<f32>();
+    assert!((sum(|m| m.production_kwh) - 4000.).abs() < 0.1);
+    assert!((sum(|m| m.consumption_kwh) - 2500.).abs() < 0.1);
+    for m in &months {
+        assert!((m.self_consumption_kwh + m.feed_in_kwh - m.production_kwh).abs() < 0.1);
+        assert!((m.self_consumption_kwh + m.grid_import_kwh - m.consumption_kwh).abs() < 0.1);
+        // no sun at midnight
+        assert_eq!(m.daily_production_kwh[0], 0.);
+    }
+    assert!(months[11].production_kwh < months[5].production_kwh / 4.);
+    // summer days are longer
+    let sun_hours = |m: &Month| m.daily_production_kwh.iter().filter(|p| **p > 0.).count();
+    assert!(sun_hours(&months[5]) > sun_hours(&months[11]) + 4);
+}
+
+#[test]
+fn battery() {
+    let without = simulate_year(&input(0.));
+    let with = simulate_year(&input(5.));
+    let sum = |hours: &[Hour], f: fn(&Hour) -> f32| hours.iter().map(f).sum::<f32>();
+    assert_eq!(sum(&without, |h| h.battery_charge_kwh), 0.);
+    assert!(
+        sum(&with, |h| h.self_consumption_kwh) > sum(&without, |h| h.self_consumption_kwh) + 300.
+    );
+    assert!(with.iter().all(|h| h.soc_kwh >= 0. && h.soc_kwh <= 5.));
+    // charged energy comes back minus losses
+    let charged = sum(&with, |h| h.battery_charge_kwh);
+    let discharged = sum(&with, |h| h.battery_discharge_kwh) + with.last().unwrap().soc_kwh;
+    assert!((discharged - charged * 0.9).abs() < 1.);
+    for h in &with {
+        let pv_used = h.self_consumption_kwh - h.battery_discharge_kwh;
+        assert!((pv_used + h.battery_charge_kwh + h.feed_in_kwh - h.production_kwh).abs() < 1e-4);
+    }
+
+    let limited = simulate_year(&SimulationInput {
+        feed_in_limit_kw: Some(1.),
+        ..input(5.)
+    });
+    assert!(limited.iter().all(|h| h.feed_in_kwh <= 1.));
+    let curtailed = sum(&limited, |h| h.curtailed_kwh);
+    assert!(curtailed > 0.);
+    assert!(
+        (sum(&limited, |h| h.feed_in_kwh) + curtailed - sum(&with, |h| h.feed_in_kwh)).abs() < 1.
+    );
+}
+
+#[test]
+fn days() {
+    let input = input(5.);
+    let hours = simulate_year(&input);
+    let days = typical_days(&input, &hours);
+    let production = |d: &Day| d.hours.iter().map(|h| h.production_kwh).sum::<f32>();
+    assert_eq!(days.len(), 4);
+    assert!(days.iter().all(|d| d.hours.len() == 24));
+    let [summer, winter, best, worst] = [0, 1, 2, 3].map(|i| &days[i]);
+    assert!(production(summer) > 3. * production(winter));
+    assert!(production(best) > production(worst));
+    assert!(month_of(worst.day_of_year) <= 1 || month_of(worst.day_of_year) >= 10);
+}
+
+#[test]
+fn east_west() {
+    // hour of the day around which the production is centered
+    let centroid = |azimuth_deg| {
+        let hours = simulate_year(&SimulationInput {
+            azimuth_deg,
+            ..input(0.)
+        });
+        let day = &hours[JUNE_SOLSTICE * 24..(JUNE_SOLSTICE + 1) * 24];
+        let sum = day.iter().map(|h| h.production_kwh).sum::<f32>();
+        day.iter()
+            .enumerate()
+            .map(|(hour, h)| (hour as f32 + 0.5) * h.production_kwh)
+            .sum::<f32>()
+            / sum
+    };
+    assert!((centroid(180.) - 12.).abs() < 0.1);
+    assert!(centroid(90.) < 11.);
+    assert!(centroid(270.) > 13.);
+}
+
+#[test]
+fn southern_hemisphere_sites() {
+    // Sydney and Johannesburg, with north facing panels
+    for latitude_deg in [-33.9, -26.2] {
+        let input = SimulationInput {
+            latitude_deg,
+            azimuth_deg: 0.,
+            ..input(5.)
+        };
+        let hours = simulate_year(&input);
+        let months = monthly(&hours);
+        assert!(months[0].production_kwh > 3. * months[6].production_kwh);
+        assert!(months[6].consumption_kwh > months[0].consumption_kwh);
+
+        let days = typical_days(&input, &hours);
+        let (summer, winter) = (&days[0], &days[1]);
+        assert_eq!(summer.day_of_year, DECEMBER_SOLSTICE);
+        let daylight = |d: &Day| d.hours.iter().filter(|h| h.production_kwh > 0.).count();
+        assert!(daylight(summer) > daylight(winter));
+        // the sun still rises in the east
+        let east = simulate_year(&SimulationInput {
+            azimuth_deg: 90.,
+            ..input
+        });
+        let summer_day = &east[DECEMBER_SOLSTICE * 24..(DECEMBER_SOLSTICE + 1) * 24];
+        let morning = summer_day[..12]
+            .iter()
+            .map(|h| h.production_kwh)
+            .sum::<f32>();
+        let afternoon = summer_day[12..]
+            .iter()
+            .map(|h| h.production_kwh)
+            .sum::<f32>();
+        assert!(morning > 1.5 * afternoon);
+    }
+}
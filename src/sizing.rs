@@ -0,0 +1,146 @@
+//! Battery sizing: the year is simulated for banks of 0, 1, 2, ... parallel strings of a
+//! library battery, to see how much storage pays off.
+
+use crate::{
+    analysis::analyze,
+    components::{Battery, Library, Project},
+    tax::CostCategory,
+};
+
+/// Results for one bank size
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SizingPoint {
+    /// Parallel strings, 0 is no battery
+    pub strings: u32,
+    pub capacity_kwh: f32,
+    pub price: f32,
+    /// 0-1, share of the consumption covered by PV
+    pub autarky: f32,
+    /// 0-1, share of the yield used on site
+    pub self_consumption: f32,
+    pub npv: f32,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Sizing {
+    pub points: Vec<SizingPoint>,
+    /// Index of the point with the best net present value
+    pub optimal: usize,
+    /// Index of the point after which more capacity barely raises autarky
+    pub knee: usize,
+}
+
+impl Sizing {
+    pub fn optimal(&self) -> Option<&SizingPoint> {
+        self.points.get(self.optimal)
+    }
+
+    pub fn knee(&self) -> Option<&SizingPoint> {
+        self.points.get(self.knee)
+    }
+}
+
+/// Simulates `project` with 0 to `max_strings` strings of `battery` instead of its own batteries
+pub fn battery_sizing(
+    project: &Project,
+    library: &Library,
+    battery: &Battery,
+    max_strings: u32,
+) -> Sizing {
+    let mut library = library.clone();
+    library.batteries.push(battery.clone());
+    let id = library.batteries.len() - 1;
+
+    let points = (0..=max_strings)
+        .map(|strings| {
+            library.batteries[id].parallel = strings.max(1);
+            let project = Project {
+                batteries: if strings == 0 { vec![] } else { vec![id] },
+                ..project.clone()
+            };
+            let analysis = analyze(&project, &library);
+            SizingPoint {
+                strings,
+                capacity_kwh: project.storage(&library).capacity_kwh,
+                price: project
+                    .batteries(&library)
+                    .filter_map(|b| {
+                        let price = b.bank_price(&project.pricing(&library))?;
+                        Some(project.tax.cost(price, CostCategory::Batteries).amount())
+                    })
+                    .sum(),
+                autarky: analysis.autarky,
+                self_consumption: analysis.self_consumption,
+                npv: analysis.npv,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Sizing {
+        optimal: points
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.npv.total_cmp(&b.npv))
+            .map(|(i, _)| i)
+            .unwrap_or_default(),
+        knee: knee(
+            &points
+                .iter()
+                .map(|p| (p.capacity_kwh, p.autarky))
+                .collect::<Vec<_>>(),
+        ),
+        points,
+    }
+}
+
+/// Index of the point of a rising, flattening curve that is furthest above the straight
+/// line from its first to its last point
+pub fn knee(curve: &[(f32, f32)]) -> usize {
+    let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+        return 0;
+    };
+    let width = (last.0 - first.0).max(f32::EPSILON);
+    let height = (last.1 - first.1).max(f32::EPSILON);
+    curve
+        .iter()
+        .enumerate()
+        .map(|(i, (x, y))| {
+            // both axes scaled to 0-1, the line is then the diagonal
+            let x = (x - first.0) / width;
+            let y = (y - first.1) / height;
+            (i, y - x)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+        .unwrap_or_default()
+}
+
+#[test]
+fn sizing() {
+    assert_eq!(
+        knee(&[(0., 0.), (1., 0.5), (2., 0.8), (3., 0.9), (4., 0.95)]),
+        2
+    );
+    assert_eq!(knee(&[]), 0);
+
+    let library = Library::default();
+    let project = Project {
+        panels: vec![0; 20],
+        inverters: vec![0],
+        panel_angle_deg: 35.,
+        consumption: crate::units::Energy::from_kwh(4250.),
+        ..Default::default()
+    };
+    let sizing = battery_sizing(&project, &library, &library.batteries[1], 6);
+    assert_eq!(sizing.points.len(), 7);
+    assert_eq!(sizing.points[0].capacity_kwh, 0.);
+    // more storage never lowers autarky, but each string adds less
+    for pair in sizing.points.windows(2) {
+        assert!(pair[1].autarky >= pair[0].autarky);
+    }
+    let gain = |i: usize| sizing.points[i + 1].autarky - sizing.points[i].autarky;
+    assert!(gain(0) > gain(5));
+    assert!(sizing.knee > 0 && sizing.knee < 6);
+    let best = sizing.optimal().unwrap().npv;
+    assert!(sizing.points.iter().all(|p| p.npv <= best));
+}
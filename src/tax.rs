@@ -0,0 +1,247 @@
+//! VAT and income tax. Library prices are net unless marked as gross; offers add the VAT of
+//! each cost category, and the economics use what the operator actually pays.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{components::BomLine, tr, units::Money};
+
+/// What a line of the bill of materials is, for its VAT rate
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CostCategory {
+    #[default]
+    Panels,
+    Inverters,
+    Batteries,
+    /// Cost items: mounting material, cables, labour, ...
+    Material,
+    /// The installation fees of the project
+    Installation,
+}
+
+impl CostCategory {
+    pub const ALL: [CostCategory; 5] = [
+        CostCategory::Panels,
+        CostCategory::Inverters,
+        CostCategory::Batteries,
+        CostCategory::Material,
+        CostCategory::Installation,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            CostCategory::Panels => tr!("panels"),
+            CostCategory::Inverters => tr!("inverters"),
+            CostCategory::Batteries => tr!("batteries"),
+            CostCategory::Material => tr!("cost_items"),
+            CostCategory::Installation => tr!("installation"),
+        }
+    }
+}
+
+/// 0-1 per [`CostCategory`]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct VatRates {
+    pub panels: f32,
+    pub inverters: f32,
+    pub batteries: f32,
+    pub material: f32,
+    pub installation: f32,
+}
+
+impl VatRates {
+    /// The same rate for everything
+    pub fn uniform(rate: f32) -> Self {
+        Self {
+            panels: rate,
+            inverters: rate,
+            batteries: rate,
+            material: rate,
+            installation: rate,
+        }
+    }
+
+    pub fn rate(&self, category: CostCategory) -> f32 {
+        match category {
+            CostCategory::Panels => self.panels,
+            CostCategory::Inverters => self.inverters,
+            CostCategory::Batteries => self.batteries,
+            CostCategory::Material => self.material,
+            CostCategory::Installation => self.installation,
+        }
+    }
+
+    pub fn rate_mut(&mut self, category: CostCategory) -> &mut f32 {
+        match category {
+            CostCategory::Panels => &mut self.panels,
+            CostCategory::Inverters => &mut self.inverters,
+            CostCategory::Batteries => &mut self.batteries,
+            CostCategory::Material => &mut self.material,
+            CostCategory::Installation => &mut self.installation,
+        }
+    }
+}
+
+/// Tax configuration of a project
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Tax {
+    /// 0-1, the regular VAT rate of the country, included in installation fees marked as gross
+    pub standard_vat_rate: f32,
+    /// VAT charged on the offer. All 0 for the German zero rate on residential systems.
+    pub vat: VatRates,
+    /// Whether the installation fees of the project include VAT at the standard rate
+    pub installation_gross: bool,
+    /// The operator reclaims the VAT as a business, so the economics use net prices
+    pub vat_deductible: bool,
+    /// 0-1, personal income tax rate on the feed-in revenue. 0 if exempt, as residential
+    /// systems up to 30 kWp in Germany.
+    pub income_tax_rate: f32,
+}
+
+impl Default for Tax {
+    fn default() -> Self {
+        Self {
+            standard_vat_rate: 0.19,
+            vat: VatRates::default(),
+            installation_gross: false,
+            vat_deductible: false,
+            income_tax_rate: 0.,
+        }
+    }
+}
+
+/// The VAT of all lines with the same rate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VatGroup {
+    pub rate: f32,
+    /// Net sum of the lines
+    pub base: Money,
+    pub vat: Money,
+}
+
+impl Tax {
+    /// What the operator pays for `net` of `category`
+    pub fn cost(&self, net: Money, category: CostCategory) -> Money {
+        if self.vat_deductible {
+            net
+        } else {
+            net * (1. + self.vat.rate(category))
+        }
+    }
+
+    /// VAT of `lines`, grouped by rate from low to high
+    pub fn vat_groups(&self, lines: &[BomLine]) -> Vec<VatGroup> {
+        let mut groups: Vec<VatGroup> = vec![];
+        for line in lines {
+            let rate = self.vat.rate(line.category);
+            match groups.iter_mut().find(|g| g.rate == rate) {
+                Some(group) => group.base += line.total,
+                None => groups.push(VatGroup {
+                    rate,
+                    base: line.total,
+                    vat: Money::default(),
+                }),
+            }
+        }
+        for group in &mut groups {
+            group.vat = group.base * group.rate;
+        }
+        groups.sort_by(|a, b| a.rate.total_cmp(&b.rate));
+        groups
+    }
+
+    /// 0-1, the VAT rate the installation fees include
+    pub fn installation_vat(&self) -> f32 {
+        if self.installation_gross {
+            self.standard_vat_rate
+        } else {
+            0.
+        }
+    }
+
+    /// Income tax on `revenue` from feed-in
+    pub fn income_tax(&self, revenue: Money) -> Money {
+        revenue * self.income_tax_rate
+    }
+}
+
+pub(crate) fn percent_edit(ui: &mut egui::Ui, ratio: &mut f32) -> egui::Response {
+    let mut percent = *ratio * 100.;
+    let response = ui.add(
+        egui::DragValue::new(&mut percent)
+            .clamp_range(0.0..=100.)
+            .speed(0.1)
+            .suffix(" %"),
+    );
+    if response.changed() {
+        *ratio = percent / 100.;
+    }
+    response
+}
+
+/// Editor for the tax configuration of a project
+pub fn tax_ui(ui: &mut egui::Ui, tax: &mut Tax) {
+    egui::Grid::new("tax").num_columns(2).show(ui, |ui| {
+        ui.label(tr!("standard_vat_rate"));
+        percent_edit(ui, &mut tax.standard_vat_rate).on_hover_text(tr!("standard_vat_rate_hint"));
+        ui.end_row();
+        for category in CostCategory::ALL {
+            ui.label(tr!("vat_on", category = category.label()));
+            percent_edit(ui, tax.vat.rate_mut(category));
+            ui.end_row();
+        }
+        ui.label("");
+        ui.checkbox(&mut tax.installation_gross, tr!("installation_gross"));
+        ui.end_row();
+        ui.label("");
+        ui.checkbox(&mut tax.vat_deductible, tr!("vat_deductible"))
+            .on_hover_text(tr!("vat_deductible_hint"));
+        ui.end_row();
+        ui.label(tr!("income_tax_rate"));
+        percent_edit(ui, &mut tax.income_tax_rate).on_hover_text(tr!("income_tax_rate_hint"));
+        ui.end_row();
+    });
+}
+
+#[test]
+fn vat() {
+    let line = |category, amount| BomLine {
+        description: String::new(),
+        quantity: 1.,
+        unit: String::new(),
+        unit_price: Money::from_amount(amount),
+        total: Money::from_amount(amount),
+        category,
+    };
+    let tax = Tax {
+        vat: VatRates {
+            installation: 0.19,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let lines = [
+        line(CostCategory::Installation, 1000.),
+        line(CostCategory::Panels, 2000.),
+        line(CostCategory::Batteries, 3000.),
+    ];
+    let groups = tax.vat_groups(&lines);
+    assert_eq!(groups.len(), 2);
+    assert_eq!((groups[0].rate, groups[0].base.amount()), (0., 5000.));
+    assert_eq!(groups[1].vat.amount(), 190.);
+
+    let installation = Money::from_amount(100.);
+    let gross = tax.cost(installation, CostCategory::Installation);
+    assert!((gross.amount() - 119.).abs() < 1e-3);
+    let business = Tax {
+        vat_deductible: true,
+        ..tax
+    };
+    assert_eq!(
+        business
+            .cost(installation, CostCategory::Installation)
+            .amount(),
+        100.
+    );
+}
@@ -0,0 +1,262 @@
+//! The hourly simulation as a table for spreadsheets, with monthly and yearly sums.
+//!
+//! The simulation works with the AC yield. DC power, plane-of-array irradiance and module
+//! temperature are derived from it: DC via a fixed inverter efficiency, irradiance as what
+//! gives that DC power at standard test conditions, and module temperature from the mean air
+//! temperature of the month, a daily swing and the NOCT heating of the modules.
+
+use crate::{
+    analysis::simulation_input,
+    components::{Library, Project},
+    quotes::Date,
+    simulation::{month_of, simulate_year, southern_hemisphere},
+};
+
+/// The reference weather is dated in this year, which like the simulation has 365 days
+const REFERENCE_YEAR: i32 = 2023;
+/// DC to AC, European efficiency of a typical string inverter
+const INVERTER_EFFICIENCY: f32 = 0.96;
+/// Mean air temperature per month in Germany in °C, DWD 1991-2020
+const AIR_TEMPERATURE: [f32; 12] = [
+    0.9, 1.6, 4.9, 9.0, 13.3, 16.4, 18.3, 17.9, 14.1, 9.6, 5.1, 1.9,
+];
+/// Difference between the warmest hour (15:00) and the daily mean, °C
+const DAILY_SWING: f32 = 4.;
+/// Heating of the modules above air temperature per W/m², from NOCT 45 °C at 800 W/m²
+const HEATING: f32 = (45. - 20.) / 800.;
+
+/// A table with a text column, e.g. the time, followed by numbers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sheet {
+    pub name: &'static str,
+    pub header: Vec<&'static str>,
+    pub rows: Vec<(String, Vec<f32>)>,
+}
+
+impl Sheet {
+    pub fn csv(&self) -> Result<String, String> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer
+            .write_record(&self.header)
+            .map_err(|e| e.to_string())?;
+        for (label, values) in &self.rows {
+            writer
+                .write_record(
+                    std::iter::once(label.clone()).chain(values.iter().map(|v| v.to_string())),
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        let data = writer.into_inner().map_err(|e| e.to_string())?;
+        String::from_utf8(data).map_err(|e| e.to_string())
+    }
+}
+
+/// Hourly values of the first columns, summed up in the monthly and yearly sheets
+const ENERGY_COLUMNS: [&str; 10] = [
+    "dc_kwh",
+    "ac_kwh",
+    "load_kwh",
+    "self_consumption_kwh",
+    "battery_charge_kwh",
+    "battery_discharge_kwh",
+    "grid_import_kwh",
+    "grid_export_kwh",
+    "curtailed_kwh",
+    "irradiation_kwh_m2",
+];
+
+/// One row per hour of the reference year
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeSeries {
+    /// Day of year and hour of day
+    pub hours: Vec<HourRow>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HourRow {
+    pub day: usize,
+    pub hour: usize,
+    /// Plane of array, W/m²
+    pub irradiance: f32,
+    /// °C
+    pub module_temperature: f32,
+    /// kWh in this hour, the same as the mean power in kW: dc, ac, load, ... as in
+    /// [`ENERGY_COLUMNS`]
+    pub energy: [f32; 9],
+    /// Battery state of charge at the end of the hour, kWh
+    pub soc: f32,
+}
+
+fn round(value: f32, decimals: i32) -> f32 {
+    let factor = 10_f32.powi(decimals);
+    (value * factor).round() / factor
+}
+
+impl TimeSeries {
+    pub fn new(project: &Project, library: &Library) -> Self {
+        let input = simulation_input(project, library);
+        let kwp = project.sum(library).peak_power.kw();
+        let shift = if southern_hemisphere(project.latitude_deg) {
+            6
+        } else {
+            0
+        };
+        let hours = simulate_year(&input)
+            .iter()
+            .enumerate()
+            .map(|(i, h)| {
+                let (day, hour) = (i / 24, i % 24);
+                let irradiance = if kwp > 0. {
+                    h.production_kwh / INVERTER_EFFICIENCY / kwp * 1000.
+                } else {
+                    0.
+                };
+                let air = AIR_TEMPERATURE[(month_of(day) + shift) % 12]
+                    + DAILY_SWING * ((hour as f32 - 15.) / 24. * std::f32::consts::TAU).cos();
+                let ac = h.production_kwh - h.curtailed_kwh;
+                HourRow {
+                    day,
+                    hour,
+                    irradiance,
+                    module_temperature: air + irradiance * HEATING,
+                    energy: [
+                        ac / INVERTER_EFFICIENCY,
+                        ac,
+                        h.consumption_kwh,
+                        h.self_consumption_kwh,
+                        h.battery_charge_kwh,
+                        h.battery_discharge_kwh,
+                        h.grid_import_kwh,
+                        h.feed_in_kwh,
+                        h.curtailed_kwh,
+                    ],
+                    soc: h.soc_kwh,
+                }
+            })
+            .collect();
+        Self { hours }
+    }
+
+    fn start() -> Date {
+        Date::new(REFERENCE_YEAR, 1, 1)
+    }
+
+    pub fn hourly(&self) -> Sheet {
+        let mut header = vec!["timestamp", "irradiance_w_m2", "module_temp_c"];
+        header.extend(&ENERGY_COLUMNS[..9]);
+        header.push("soc_kwh");
+        Sheet {
+            name: "hourly",
+            header,
+            rows: self
+                .hours
+                .iter()
+                .map(|h| {
+                    let date = Self::start().plus_days(h.day as i32);
+                    let mut values = vec![round(h.irradiance, 1), round(h.module_temperature, 1)];
+                    values.extend(h.energy.iter().map(|e| round(*e, 3)));
+                    values.push(round(h.soc, 3));
+                    (format!("{date} {:02}:00", h.hour), values)
+                })
+                .collect(),
+        }
+    }
+
+    /// Sums of the hours grouped by `period`
+    fn sums(&self, name: &'static str, period: impl Fn(&HourRow) -> String) -> Sheet {
+        let mut rows: Vec<(String, Vec<f32>)> = vec![];
+        for hour in &self.hours {
+            let label = period(hour);
+            if rows.last().map_or(true, |(last, _)| *last != label) {
+                rows.push((label, vec![0.; ENERGY_COLUMNS.len()]));
+            }
+            let (_, sums) = rows.last_mut().expect("just pushed");
+            for (sum, value) in sums.iter_mut().zip(&hour.energy) {
+                *sum += value;
+            }
+            sums[9] += hour.irradiance / 1000.;
+        }
+        for (_, sums) in &mut rows {
+            sums.iter_mut().for_each(|v| *v = round(*v, 2));
+        }
+        let mut header = vec!["period"];
+        header.extend(ENERGY_COLUMNS);
+        Sheet { name, header, rows }
+    }
+
+    pub fn monthly(&self) -> Sheet {
+        self.sums("monthly", |h| {
+            let (year, month, _) = Self::start().plus_days(h.day as i32).ymd();
+            format!("{year}-{month:02}")
+        })
+    }
+
+    pub fn yearly(&self) -> Sheet {
+        self.sums("yearly", |_| REFERENCE_YEAR.to_string())
+    }
+
+    /// A workbook with the hourly, monthly and yearly sheets
+    #[cfg(feature = "xlsx")]
+    pub fn xlsx(&self) -> Result<Vec<u8>, String> {
+        use rust_xlsxwriter::{Format, Workbook, XlsxError};
+
+        let write = || -> Result<Vec<u8>, XlsxError> {
+            let mut workbook = Workbook::new();
+            let bold = Format::new().set_bold();
+            for sheet in [self.hourly(), self.monthly(), self.yearly()] {
+                let worksheet = workbook.add_worksheet().set_name(sheet.name)?;
+                for (col, title) in sheet.header.iter().enumerate() {
+                    worksheet.write_string_with_format(0, col as u16, *title, &bold)?;
+                }
+                for (row, (label, values)) in sheet.rows.iter().enumerate() {
+                    let row = row as u32 + 1;
+                    worksheet.write_string(row, 0, label)?;
+                    for (col, value) in values.iter().enumerate() {
+                        worksheet.write_number(row, col as u16 + 1, *value)?;
+                    }
+                }
+                worksheet.set_column_width(0, 17)?;
+                worksheet.set_freeze_panes(1, 1)?;
+            }
+            workbook.save_to_buffer()
+        };
+        write().map_err(|e| e.to_string())
+    }
+}
+
+#[test]
+fn time_series() {
+    use crate::analysis::analyze;
+
+    let library = Library::default();
+    let project = Project {
+        panels: vec![0; 10],
+        batteries: vec![0],
+        inverters: vec![1],
+        panel_angle_deg: 35.,
+        ..Default::default()
+    };
+    let series = TimeSeries::new(&project, &library);
+    let hourly = series.hourly();
+    assert_eq!(hourly.rows.len(), 8760);
+    assert_eq!(hourly.rows[8759].0, "2023-12-31 23:00");
+    // 4 kWp in the midday sun of June
+    let noon = &hourly.rows[170 * 24 + 12].1;
+    assert!(noon[0] > 300. && noon[0] < 1100.);
+    assert!(noon[1] > 20.);
+
+    let monthly = series.monthly();
+    let yearly = series.yearly();
+    assert_eq!(monthly.rows.len(), 12);
+    assert_eq!(monthly.rows[1].0, "2023-02");
+    assert_eq!(yearly.rows.len(), 1);
+    let analysis = analyze(&project, &library);
+    let ac = yearly.rows[0].1[1];
+    assert!((ac - analysis.yield_year_kwh).abs() < 1.);
+    let monthly_ac = monthly.rows.iter().map(|(_, v)| v[1]).sum::<f32>();
+    assert!((monthly_ac - ac).abs() < 0.5);
+
+    let csv = yearly.csv().unwrap();
+    assert!(csv.starts_with("period,dc_kwh,ac_kwh,"));
+    assert_eq!(csv.lines().count(), 2);
+}
@@ -1,48 +1,292 @@
-use std::collections::HashMap;
+//! Runtime translations. Messages are looked up by ID in the catalogue embedded from
+//! `translations.json` and named placeholders like `{count}` are filled in:
+//!
+//! ```ignore
+//! ui.label(tr!("panels_installed", count = 10, area = "20.0"));
+//! ```
 
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::{OnceLock, RwLock},
+};
 
-// #[derive(Serialize, Deserialize, Debug, Default, Clone)]
-// struct Dictionary {
-//     translations: HashMap<String, HashMap<String>>,
-// }
+/// Message ID -> locale -> text
+pub type Catalogue = BTreeMap<String, BTreeMap<String, String>>;
+
+/// Locales with a translation, with their display name. The first one is the fallback.
+pub const LOCALES: [(&str, &str); 2] = [("de", "Deutsch"), ("en", "English")];
+
+pub const FALLBACK_LOCALE: &str = LOCALES[0].0;
+
+static LOCALE: RwLock<String> = RwLock::new(String::new());
 
 #[macro_export]
 macro_rules! tr {
+    ($id:literal) => {
+        $crate::translation::translate($id, &[])
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::translation::translate($id, &[$((stringify!($name), $value.to_string())),+])
+    };
+}
+
+pub fn catalogue() -> &'static Catalogue {
+    static CATALOGUE: OnceLock<Catalogue> = OnceLock::new();
+    CATALOGUE.get_or_init(|| {
+        serde_json::from_str(include_str!("../translations.json"))
+            .expect("translations.json must load")
+    })
+}
+
+/// Maps e.g. "en-US" to "en", or to the fallback if there is no such translation
+fn supported(locale: &str) -> &'static str {
+    let language = locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    LOCALES
+        .iter()
+        .map(|(l, _)| *l)
+        .find(|l| *l == language)
+        .unwrap_or(FALLBACK_LOCALE)
+}
+
+/// The language of the operating system or browser
+pub fn system_locale() -> &'static str {
+    supported(&sys_locale::get_locale().unwrap_or_default())
+}
 
-    ($($arg:tt)*) => {{
+/// Sets the locale for all following translations. `None` uses the system locale.
+pub fn set_locale(locale: Option<&str>) {
+    let locale = locale.map(supported).unwrap_or_else(system_locale);
+    if let Ok(mut current) = LOCALE.write() {
+        *current = locale.to_string();
+    }
+}
 
+pub fn locale() -> String {
+    match LOCALE.read() {
+        Ok(locale) if !locale.is_empty() => locale.clone(),
+        _ => system_locale().to_string(),
+    }
+}
+
+/// Looks up `id` in `locale`, falling back to German and then to the ID itself. Empty
+/// texts, as inserted by [`update_catalogue`], count as missing.
+pub fn lookup<'a>(catalogue: &'a Catalogue, id: &'a str, locale: &str) -> &'a str {
+    let text = |texts: &'a BTreeMap<String, String>, locale: &str| {
+        texts
+            .get(locale)
+            .map(String::as_str)
+            .filter(|t| !t.is_empty())
+    };
+    catalogue
+        .get(id)
+        .and_then(|texts| text(texts, locale).or_else(|| text(texts, FALLBACK_LOCALE)))
+        .unwrap_or(id)
+}
+
+/// Replaces `{name}` placeholders
+pub fn fill(text: &str, args: &[(&str, String)]) -> String {
+    let mut text = text.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), value);
+    }
+    text
+}
+
+/// Translates `id` into the current locale. Use through the `tr!` macro.
+pub fn translate(id: &str, args: &[(&str, String)]) -> String {
+    fill(lookup(catalogue(), id, &locale()), args)
+}
 
-        if false
-        {
-            let mut dict : std::collections::HashMap<String, std::collections::HashMap<String,String>> = std::fs::File::open("translations.json").map(|f| serde_json::from_reader(f).unwrap_or_default()).unwrap_or_default();
-            let pattern_str = stringify!($($arg)*).to_string().replace("\"","");
-            let args =  pattern_str.split(',').nth(0).unwrap_or(&pattern_str);
-            println!("Pattern before expansion: {}", args);
-            let locale = sys_locale::get_locale().unwrap_or("de".to_string());
-            dict.entry(args.to_string()).or_default().insert(locale, args.to_string());
-            _ = serde_json::to_writer_pretty(std::fs::File::create("translations.json").unwrap(), &dict);
+/// Message IDs are lower case snake case
+fn is_message_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Message IDs of all `tr!` invocations in a piece of source code
+pub fn extract_ids(source: &str) -> BTreeSet<String> {
+    let mut ids = BTreeSet::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("tr!(") {
+        // skip other macros ending in "tr", like `include_str!`
+        let part_of_other_macro = rest[..start]
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        rest = rest[start + 4..].trim_start();
+        if part_of_other_macro {
+            continue;
+        }
+        if let Some(literal) = rest.strip_prefix('"') {
+            if let Some(id) = literal.split('"').next().filter(|id| is_message_id(id)) {
+                ids.insert(id.to_string());
+            }
         }
-        
-       
-        let res = format!($($arg)*);
-        // let res = format!($($arg)*);
+    }
+    ids
+}
 
+/// Message IDs used in all `.rs` files below `dir`
+pub fn scan_sources(dir: &Path) -> std::io::Result<BTreeSet<String>> {
+    let mut ids = BTreeSet::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            ids.extend(scan_sources(&path)?);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            ids.extend(extract_ids(&std::fs::read_to_string(&path)?));
+        }
+    }
+    Ok(ids)
+}
 
-        // dict.insert(locale, )
+/// How the catalogue relates to the IDs used in code
+#[derive(Debug, Default)]
+pub struct CatalogueReport {
+    /// Used in code, but not in the catalogue
+    pub new: Vec<String>,
+    /// In the catalogue, but not used in code anymore
+    pub stale: Vec<String>,
+    /// Per locale: used IDs without a text
+    pub missing: BTreeMap<String, Vec<String>>,
+    /// Number of IDs used in code
+    pub used: usize,
+}
 
-        res
-    }}
+impl CatalogueReport {
+    /// Share of used IDs translated for `locale`, 0-1
+    pub fn completeness(&self, locale: &str) -> f32 {
+        let missing = self
+            .missing
+            .get(locale)
+            .map(|m| m.len())
+            .unwrap_or_default();
+        if self.used == 0 {
+            1.
+        } else {
+            1. - missing as f32 / self.used as f32
+        }
+    }
 }
 
+/// Compares the catalogue with the IDs used in code
+pub fn check_catalogue(catalogue: &Catalogue, used: &BTreeSet<String>) -> CatalogueReport {
+    CatalogueReport {
+        new: used
+            .iter()
+            .filter(|id| !catalogue.contains_key(*id))
+            .cloned()
+            .collect(),
+        stale: catalogue
+            .keys()
+            .filter(|id| !used.contains(*id))
+            .cloned()
+            .collect(),
+        missing: LOCALES
+            .iter()
+            .map(|(locale, _)| {
+                let missing = used
+                    .iter()
+                    .filter(|id| {
+                        catalogue
+                            .get(*id)
+                            .and_then(|texts| texts.get(*locale))
+                            .map_or(true, |text| text.is_empty())
+                    })
+                    .cloned()
+                    .collect();
+                (locale.to_string(), missing)
+            })
+            .collect(),
+        used: used.len(),
+    }
+}
 
+/// Adds new IDs with empty texts for every locale, and removes stale ones if `prune` is set
+pub fn update_catalogue(catalogue: &mut Catalogue, used: &BTreeSet<String>, prune: bool) {
+    for id in used {
+        let texts = catalogue.entry(id.clone()).or_default();
+        for (locale, _) in LOCALES {
+            texts.entry(locale.to_string()).or_default();
+        }
+    }
+    if prune {
+        catalogue.retain(|id, _| used.contains(id));
+    }
+}
+
+#[test]
+fn translate_messages() {
+    let catalogue: Catalogue = serde_json::from_str(
+        r#"{
+            "hello": { "de": "Hallo {name}", "en": "Hello {name}" },
+            "only_de": { "de": "Nur deutsch" },
+            "untranslated": { "de": "Neu", "en": "" }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(lookup(&catalogue, "hello", "en"), "Hello {name}");
+    assert_eq!(lookup(&catalogue, "only_de", "en"), "Nur deutsch");
+    assert_eq!(lookup(&catalogue, "untranslated", "en"), "Neu");
+    assert_eq!(lookup(&catalogue, "missing", "en"), "missing");
+    assert_eq!(
+        fill("Hallo {name}, {name}!", &[("name", "Welt".into())]),
+        "Hallo Welt, Welt!"
+    );
+    assert_eq!(supported("en-US"), "en");
+    assert_eq!(supported("de_AT"), "de");
+    assert_eq!(supported("fr-FR"), FALLBACK_LOCALE);
+}
 
+#[test]
+fn extract() {
+    let source = r#"
+        ui.label(tr!("brand"));
+        ui.label(tr!(
+            "panels_installed",
+            count = 3
+        ));
+        let not_a_message = "tr!(";
+        include_str!("../translations.json");
+    "#;
+    let ids = extract_ids(source);
+    assert_eq!(
+        ids.into_iter().collect::<Vec<_>>(),
+        vec!["brand".to_string(), "panels_installed".to_string()]
+    );
 
-// #[macro_export]
-// macro_rules! tr {
-//     ($($arg:tt)*) => {{
-//         let input_str = stringify!($($arg)*);
-//         println!("Input before expansion: {}", input_str);
-//         let res = format!($($arg)*);
-//         res
-//     }}
-// }
\ No newline at end of file
+    let mut catalogue: Catalogue =
+        serde_json::from_str(r#"{ "brand": { "de": "Marke" }, "old": { "de": "Alt" } }"#).unwrap();
+    let used = BTreeSet::from(["brand".to_string(), "model".to_string()]);
+    let report = check_catalogue(&catalogue, &used);
+    assert_eq!(report.new, vec!["model"]);
+    assert_eq!(report.stale, vec!["old"]);
+    assert_eq!(report.missing["de"], vec!["model"]);
+    assert_eq!(report.completeness("de"), 0.5);
+    assert_eq!(report.completeness("en"), 0.);
+
+    update_catalogue(&mut catalogue, &used, true);
+    assert!(!catalogue.contains_key("old"));
+    assert_eq!(catalogue["model"]["en"], "");
+}
+
+/// Fails if a message used in code is not translated for every supported locale
+#[test]
+fn catalogue_complete() {
+    let used = scan_sources(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src")).unwrap();
+    assert!(used.contains("disclaimer"));
+    let report = check_catalogue(catalogue(), &used);
+    for (locale, missing) in &report.missing {
+        assert!(
+            missing.is_empty(),
+            "Missing {locale} translations: {missing:?}"
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Physical units and money. Values only get in and out through constructors and
+//! accessors that name the unit, so Wp can't be mistaken for kWp, or Ah for kWh.
+//!
+//! On disk the values keep their historic units, see the serde modules at the bottom.
+
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Div, Mul, Sub},
+};
+
+use egui::DragValue;
+
+use crate::currency::Currency;
+
+macro_rules! unit {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+        pub struct $name(f32);
+
+        impl Add for $name {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: Self) {
+                self.0 += rhs.0;
+            }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = Self;
+            fn mul(self, rhs: f32) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl Div<f32> for $name {
+            type Output = Self;
+            fn div(self, rhs: f32) -> Self {
+                Self(self.0 / rhs)
+            }
+        }
+
+        /// The ratio of two values of the same unit
+        impl Div for $name {
+            type Output = f32;
+            fn div(self, rhs: Self) -> f32 {
+                self.0 / rhs.0
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                Self(iter.map(|v| v.0).sum())
+            }
+        }
+
+        impl<'a> Sum<&'a $name> for $name {
+            fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                iter.copied().sum()
+            }
+        }
+    };
+}
+
+unit!(
+    /// Electrical power, e.g. the peak power of a panel
+    Power
+);
+unit!(
+    /// Electrical energy
+    Energy
+);
+unit!(
+    /// Battery charge
+    Charge
+);
+unit!(Voltage);
+unit!(Area);
+unit!(
+    /// An amount of money in the currency of its context: the item's for library prices,
+    /// the project's for everything calculated
+    Money
+);
+unit!(
+    /// Price of energy, e.g. the electricity tariff
+    EnergyPrice
+);
+
+impl Power {
+    pub fn from_w(w: f32) -> Self {
+        Self(w)
+    }
+    pub fn from_kw(kw: f32) -> Self {
+        Self(kw * 1000.)
+    }
+    pub fn w(&self) -> f32 {
+        self.0
+    }
+    pub fn kw(&self) -> f32 {
+        self.0 / 1000.
+    }
+    /// Energy when running at this power for `hours`
+    pub fn over_hours(&self, hours: f32) -> Energy {
+        Energy::from_kwh(self.kw() * hours)
+    }
+}
+
+impl Energy {
+    pub fn from_kwh(kwh: f32) -> Self {
+        Self(kwh)
+    }
+    pub fn from_wh(wh: f32) -> Self {
+        Self(wh / 1000.)
+    }
+    pub fn kwh(&self) -> f32 {
+        self.0
+    }
+    pub fn wh(&self) -> f32 {
+        self.0 * 1000.
+    }
+}
+
+impl Charge {
+    pub fn from_ah(ah: f32) -> Self {
+        Self(ah)
+    }
+    pub fn ah(&self) -> f32 {
+        self.0
+    }
+}
+
+impl Voltage {
+    pub fn from_v(v: f32) -> Self {
+        Self(v)
+    }
+    pub fn v(&self) -> f32 {
+        self.0
+    }
+}
+
+impl Area {
+    pub fn from_m2(m2: f32) -> Self {
+        Self(m2)
+    }
+    pub fn from_cm2(cm2: f32) -> Self {
+        Self(cm2 / 10_000.)
+    }
+    pub fn m2(&self) -> f32 {
+        self.0
+    }
+}
+
+impl Money {
+    pub fn from_amount(amount: f32) -> Self {
+        Self(amount)
+    }
+    pub fn amount(&self) -> f32 {
+        self.0
+    }
+}
+
+impl EnergyPrice {
+    pub fn from_amount_per_kwh(amount: f32) -> Self {
+        Self(amount)
+    }
+    pub fn amount_per_kwh(&self) -> f32 {
+        self.0
+    }
+}
+
+/// Charge at a voltage is energy
+impl Mul<Voltage> for Charge {
+    type Output = Energy;
+    fn mul(self, rhs: Voltage) -> Energy {
+        Energy::from_wh(self.ah() * rhs.v())
+    }
+}
+
+/// What an amount of energy costs
+impl Mul<EnergyPrice> for Energy {
+    type Output = Money;
+    fn mul(self, rhs: EnergyPrice) -> Money {
+        Money::from_amount(self.kwh() * rhs.amount_per_kwh())
+    }
+}
+
+/// Edits `value` in the unit given by `get` and `set`
+fn drag<T>(value: &mut T, get: fn(&T) -> f32, set: fn(f32) -> T) -> DragValue<'_> {
+    DragValue::from_get_set(move |new| {
+        if let Some(new) = new {
+            *value = set(new as f32);
+        }
+        get(value) as f64
+    })
+}
+
+impl Power {
+    pub fn drag_w(&mut self) -> DragValue<'_> {
+        drag(self, Self::w, Self::from_w).suffix(" W")
+    }
+    pub fn drag_kw(&mut self) -> DragValue<'_> {
+        drag(self, Self::kw, Self::from_kw).suffix(" kW")
+    }
+}
+
+impl Energy {
+    pub fn drag_kwh(&mut self) -> DragValue<'_> {
+        drag(self, Self::kwh, Self::from_kwh).suffix(" kWh")
+    }
+}
+
+impl Charge {
+    pub fn drag_ah(&mut self) -> DragValue<'_> {
+        drag(self, Self::ah, Self::from_ah).suffix(" Ah")
+    }
+}
+
+impl Voltage {
+    pub fn drag_v(&mut self) -> DragValue<'_> {
+        drag(self, Self::v, Self::from_v).suffix(" V")
+    }
+}
+
+impl Area {
+    pub fn drag_m2(&mut self) -> DragValue<'_> {
+        drag(self, Self::m2, Self::from_m2).suffix(" m²")
+    }
+}
+
+impl Money {
+    pub fn drag_in(&mut self, currency: &Currency) -> DragValue<'_> {
+        drag(self, Self::amount, Self::from_amount).suffix(format!(" {}", currency.symbol()))
+    }
+}
+
+impl EnergyPrice {
+    pub fn drag_in(&mut self, currency: &Currency) -> DragValue<'_> {
+        drag(self, Self::amount_per_kwh, Self::from_amount_per_kwh)
+            .suffix(format!(" {}/kWh", currency.symbol()))
+    }
+}
+
+macro_rules! serde_unit {
+    ($(#[$doc:meta])* $module:ident, $unit:ident, $get:ident, $from:ident) => {
+        $(#[$doc])*
+        pub mod $module {
+            use serde::{Deserialize, Deserializer, Serializer};
+
+            pub fn serialize<S: Serializer>(value: &super::$unit, s: S) -> Result<S::Ok, S::Error> {
+                s.serialize_f32(value.$get())
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<super::$unit, D::Error> {
+                f32::deserialize(d).map(super::$unit::$from)
+            }
+        }
+    };
+}
+
+serde_unit!(
+    /// `#[serde(with = "units::watts")]` stores power as a plain number of W
+    watts,
+    Power,
+    w,
+    from_w
+);
+serde_unit!(kilowatts, Power, kw, from_kw);
+serde_unit!(kilowatt_hours, Energy, kwh, from_kwh);
+serde_unit!(amp_hours, Charge, ah, from_ah);
+serde_unit!(volts, Voltage, v, from_v);
+serde_unit!(square_meters, Area, m2, from_m2);
+serde_unit!(money, Money, amount, from_amount);
+serde_unit!(
+    money_per_kwh,
+    EnergyPrice,
+    amount_per_kwh,
+    from_amount_per_kwh
+);
+
+#[test]
+fn conversions() {
+    assert_eq!(Power::from_w(400.).kw(), 0.4);
+    assert_eq!(Power::from_kw(4.2).w(), 4200.);
+    assert_eq!([Power::from_w(400.); 10].iter().sum::<Power>().kw(), 4.);
+    assert_eq!((Charge::from_ah(200.) * Voltage::from_v(12.)).kwh(), 2.4);
+    assert!((Area::from_cm2(114.4 * 175.2).m2() - 2.004_288).abs() < 1e-5);
+    assert_eq!(
+        (Energy::from_kwh(100.) * EnergyPrice::from_amount_per_kwh(0.5)).amount(),
+        50.
+    );
+    assert_eq!(Power::from_kw(2.).over_hours(3.).kwh(), 6.);
+    assert_eq!(Money::from_amount(10.) / Money::from_amount(4.), 2.5);
+}